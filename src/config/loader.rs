@@ -1,29 +1,218 @@
+use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
+
+use crate::tado::client::{
+    HttpVersion, DEFAULT_CLIENT_ID, DEFAULT_MAX_RESPONSE_SIZE, DEFAULT_TCP_KEEPALIVE,
+};
+use crate::tado::collector::{DEFAULT_VALVE_CLOSE_THRESHOLD, DEFAULT_VALVE_OPEN_THRESHOLD};
+use crate::tado::endpoints::Endpoints;
+use crate::tado::metrics::{ZoneLabel, DEFAULT_NAMESPACE};
 
 pub struct Config {
     pub ticker: u64,
+    pub cron: String,
+    pub zone_intervals: HashMap<String, u64>,
+    pub zone_groups: HashMap<String, String>,
+    pub zone_ids: Vec<i32>,
+    pub zone_eviction_grace: u64,
+    pub zone_label: ZoneLabel,
     pub username: String,
     pub password: String,
+    pub password_file: String,
+    pub secondary_username: String,
+    pub secondary_password: String,
+    pub credentials_refresh: u64,
+    pub cache_file: String,
+    pub token_file: String,
+    pub auth_fail_open: bool,
+    pub background_renewal: bool,
     pub client_secret: String,
+    pub client_ids: Vec<String>,
+    pub skip_stale_samples: bool,
+    pub hold_on_failure: bool,
+    pub endpoints: Endpoints,
+    // None to derive the deadline from the ticker in use.
+    pub scrape_deadline: Option<f64>,
+    pub collect_schedules: bool,
+    pub collect_devices: bool,
+    pub collect_mobile_devices: bool,
+    pub collect_home_state: bool,
+    pub home_location: bool,
+    pub connect_timeout: f64,
+    pub request_timeout: f64,
+    pub http_version: HttpVersion,
+    pub tcp_keepalive: u64,
+    pub max_response_size: usize,
+    pub request_retries: u32,
+    pub retry_budget: u32,
+    pub circuit_breaker_threshold: u32,
+    pub circuit_breaker_cooldown: u64,
+    pub weather_failure_threshold: u32,
+    pub weather_failure_cooldown: u64,
+    pub temperature_filter: bool,
+    pub temperature_min: f64,
+    pub temperature_max: f64,
+    pub humidity_alert: bool,
+    pub humidity_min: f64,
+    pub humidity_max: f64,
+    pub valve_open_threshold: f64,
+    pub valve_close_threshold: f64,
+    pub app_temperatures: bool,
+    pub metric_namespace: String,
+    pub metrics_allow: Vec<String>,
+    pub metrics_deny: Vec<String>,
+    pub http_server: bool,
+    pub base_path: String,
+    pub metrics_path: String,
+    pub debug_logs: usize,
+    pub history: usize,
+    pub log_requests: bool,
+    pub pushgateway_url: String,
+    pub pushgateway_job: String,
+    pub pushgateway_instance: String,
 }
 
 impl Config {
     pub fn print(&self) {
         println!("--- tado° exporter configuration ---");
         println!("Ticker seconds: {}", self.ticker);
+        println!("Cron: {}", self.cron);
+        println!("Zone intervals seconds: {:?}", self.zone_intervals);
+        println!("Zone groups: {:?}", self.zone_groups);
+        println!("Zone ids: {:?}", self.zone_ids);
+        println!("Zone eviction grace seconds: {}", self.zone_eviction_grace);
+        println!("Zone label: {:?}", self.zone_label);
         println!("Username: {}", self.username);
         println!("Password: <not printed>");
+        println!("Password file: {}", self.password_file);
+        println!("Secondary username: {}", self.secondary_username);
+        println!("Secondary password: <not printed>");
+        println!("Credentials refresh seconds: {}", self.credentials_refresh);
+        println!("Cache file: {}", self.cache_file);
+        println!("Token file: {}", self.token_file);
+        println!("Auth fail open: {}", self.auth_fail_open);
+        println!("Background renewal: {}", self.background_renewal);
         println!("Client secret: {}", self.client_secret);
+        println!("Client ids: {:?}", self.client_ids);
+        println!("Skip stale samples: {}", self.skip_stale_samples);
+        println!("Hold on failure: {}", self.hold_on_failure);
+        println!("Endpoint (me): {}", self.endpoints.me);
+        println!("Endpoint (home): {}", self.endpoints.home);
+        println!("Endpoint (zones): {}", self.endpoints.zones);
+        println!("Endpoint (zone state): {}", self.endpoints.zone_state);
+        println!("Endpoint (weather): {}", self.endpoints.weather);
+        println!("Endpoint (devices): {}", self.endpoints.devices);
+        println!(
+            "Endpoint (active timetable): {}",
+            self.endpoints.active_timetable
+        );
+        println!(
+            "Endpoint (timetable blocks): {}",
+            self.endpoints.timetable_blocks
+        );
+        println!(
+            "Endpoint (away configuration): {}",
+            self.endpoints.away_configuration
+        );
+        println!(
+            "Endpoint (mobile devices): {}",
+            self.endpoints.mobile_devices
+        );
+        println!("Endpoint (home state): {}", self.endpoints.home_state);
+        match self.scrape_deadline {
+            Some(scrape_deadline) => println!("Scrape deadline seconds: {}", scrape_deadline),
+            None => println!("Scrape deadline seconds: 80% of the ticker"),
+        }
+        println!("Collect schedules: {}", self.collect_schedules);
+        println!("Collect devices: {}", self.collect_devices);
+        println!("Collect mobile devices: {}", self.collect_mobile_devices);
+        println!("Collect home state: {}", self.collect_home_state);
+        println!("Home location: {}", self.home_location);
+        println!("Connect timeout seconds: {}", self.connect_timeout);
+        println!("Request timeout seconds: {}", self.request_timeout);
+        println!("HTTP version: {:?}", self.http_version);
+        println!("TCP keepalive seconds: {}", self.tcp_keepalive);
+        println!("Max response size bytes: {}", self.max_response_size);
+        println!("Request retries: {}", self.request_retries);
+        println!("Retry budget: {}", self.retry_budget);
+        println!(
+            "Circuit breaker threshold: {}",
+            self.circuit_breaker_threshold
+        );
+        println!(
+            "Circuit breaker cooldown seconds: {}",
+            self.circuit_breaker_cooldown
+        );
+        println!(
+            "Weather failure threshold: {}",
+            self.weather_failure_threshold
+        );
+        println!(
+            "Weather failure cooldown seconds: {}",
+            self.weather_failure_cooldown
+        );
+        println!("Temperature filter: {}", self.temperature_filter);
+        println!("Temperature min: {}", self.temperature_min);
+        println!("Temperature max: {}", self.temperature_max);
+        println!("Humidity alert: {}", self.humidity_alert);
+        println!("Humidity min: {}", self.humidity_min);
+        println!("Humidity max: {}", self.humidity_max);
+        println!("Valve open threshold: {}", self.valve_open_threshold);
+        println!("Valve close threshold: {}", self.valve_close_threshold);
+        println!("App temperatures: {}", self.app_temperatures);
+        println!("Metric namespace: {}", self.metric_namespace);
+        println!("Metrics allowed: {:?}", self.metrics_allow);
+        println!("Metrics denied: {:?}", self.metrics_deny);
+        println!("HTTP server: {}", self.http_server);
+        println!("Base path: {}", self.base_path);
+        println!("Metrics path: {}", self.metrics_path);
+        println!("Debug log lines: {}", self.debug_logs);
+        println!("History scrapes: {}", self.history);
+        println!("Log requests: {}", self.log_requests);
+        println!("Pushgateway URL: {}", self.pushgateway_url);
+        println!("Pushgateway job: {}", self.pushgateway_job);
+        println!("Pushgateway instance: {}", self.pushgateway_instance);
         println!("------------------------------------");
     }
 }
 
 pub fn load() -> Config {
+    let default_endpoints = Endpoints::default();
+
     let config = Config {
         ticker: match env::var("EXPORTER_TICKER") {
             Ok(v) => v.parse::<u64>().unwrap(),
             Err(_) => 10,
         },
+        cron: env::var("EXPORTER_CRON").unwrap_or_default(),
+        zone_intervals: match env::var("EXPORTER_ZONE_INTERVALS") {
+            Ok(v) => parse_zone_settings(&v)
+                .into_iter()
+                .map(|(zone, seconds)| (zone, seconds.parse::<u64>().unwrap()))
+                .collect(),
+            Err(_) => HashMap::new(),
+        },
+        zone_groups: match env::var("EXPORTER_ZONE_GROUPS") {
+            Ok(v) => parse_zone_settings(&v),
+            Err(_) => HashMap::new(),
+        },
+        zone_ids: match env::var("EXPORTER_ZONE_IDS") {
+            Ok(v) => v
+                .split(',')
+                .filter(|id| !id.trim().is_empty())
+                .map(|id| id.trim().parse::<i32>().unwrap())
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+        zone_eviction_grace: match env::var("EXPORTER_ZONE_EVICTION_GRACE") {
+            Ok(v) => v.parse::<u64>().unwrap(),
+            Err(_) => 3600,
+        },
+        zone_label: match env::var("EXPORTER_ZONE_LABEL") {
+            Ok(v) => v.parse::<ZoneLabel>().unwrap(),
+            Err(_) => ZoneLabel::Name,
+        },
         username: match env::var("EXPORTER_USERNAME") {
             Ok(v) => v,
             Err(_) => "".to_string(),
@@ -32,12 +221,273 @@ pub fn load() -> Config {
             Ok(v) => v,
             Err(_) => "".to_string(),
         },
+        password_file: match env::var("EXPORTER_PASSWORD_FILE") {
+            Ok(v) => v,
+            Err(_) => "".to_string(),
+        },
+        secondary_username: match env::var("EXPORTER_SECONDARY_USERNAME") {
+            Ok(v) => v,
+            Err(_) => "".to_string(),
+        },
+        secondary_password: match env::var("EXPORTER_SECONDARY_PASSWORD") {
+            Ok(v) => v,
+            Err(_) => "".to_string(),
+        },
+        credentials_refresh: match env::var("EXPORTER_CREDENTIALS_REFRESH") {
+            Ok(v) => v.parse::<u64>().unwrap(),
+            Err(_) => 300,
+        },
+        cache_file: match env::var("EXPORTER_CACHE_FILE") {
+            Ok(v) => v,
+            Err(_) => "".to_string(),
+        },
+        token_file: match env::var("EXPORTER_TOKEN_FILE") {
+            Ok(v) => v,
+            Err(_) => "".to_string(),
+        },
+        auth_fail_open: match env::var("EXPORTER_AUTH_FAIL_OPEN") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => true,
+        },
+        background_renewal: match env::var("EXPORTER_BACKGROUND_RENEWAL") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => true,
+        },
         client_secret: match env::var("EXPORTER_CLIENT_SECRET") {
             Ok(v) => v,
             Err(_) => {
                 "wZaRN7rpjn3FoNyF5IFuxg9uMzYJcvOoQ8QWiIqS3hfk6gLhVlG57j5YNoZL2Rtc".to_string()
             }
         },
+        client_ids: match env::var("EXPORTER_CLIENT_IDS") {
+            Ok(v) => v
+                .split(',')
+                .map(|id| id.trim())
+                .filter(|id| !id.is_empty())
+                .map(|id| id.to_string())
+                .collect(),
+            Err(_) => vec![DEFAULT_CLIENT_ID.to_string()],
+        },
+        skip_stale_samples: match env::var("EXPORTER_SKIP_STALE_SAMPLES") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        hold_on_failure: match env::var("EXPORTER_HOLD_ON_FAILURE") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        endpoints: Endpoints {
+            me: match env::var("EXPORTER_ENDPOINT_ME") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.me,
+            },
+            home: match env::var("EXPORTER_ENDPOINT_HOME") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.home,
+            },
+            zones: match env::var("EXPORTER_ENDPOINT_ZONES") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.zones,
+            },
+            zone_state: match env::var("EXPORTER_ENDPOINT_ZONE_STATE") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.zone_state,
+            },
+            weather: match env::var("EXPORTER_ENDPOINT_WEATHER") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.weather,
+            },
+            devices: match env::var("EXPORTER_ENDPOINT_DEVICES") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.devices,
+            },
+            active_timetable: match env::var("EXPORTER_ENDPOINT_ACTIVE_TIMETABLE") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.active_timetable,
+            },
+            timetable_blocks: match env::var("EXPORTER_ENDPOINT_TIMETABLE_BLOCKS") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.timetable_blocks,
+            },
+            away_configuration: match env::var("EXPORTER_ENDPOINT_AWAY_CONFIGURATION") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.away_configuration,
+            },
+            mobile_devices: match env::var("EXPORTER_ENDPOINT_MOBILE_DEVICES") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.mobile_devices,
+            },
+            home_state: match env::var("EXPORTER_ENDPOINT_HOME_STATE") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.home_state,
+            },
+            day_report: match env::var("EXPORTER_ENDPOINT_DAY_REPORT") {
+                Ok(v) => v,
+                Err(_) => default_endpoints.day_report,
+            },
+        },
+        scrape_deadline: env::var("EXPORTER_SCRAPE_DEADLINE")
+            .ok()
+            .map(|v| parse_scrape_deadline(&v)),
+        collect_schedules: match env::var("EXPORTER_COLLECT_SCHEDULES") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        collect_devices: match env::var("EXPORTER_COLLECT_DEVICES") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        collect_mobile_devices: match env::var("EXPORTER_COLLECT_MOBILE_DEVICES") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        collect_home_state: match env::var("EXPORTER_COLLECT_HOME_STATE") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        home_location: match env::var("EXPORTER_HOME_LOCATION") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        connect_timeout: match env::var("EXPORTER_CONNECT_TIMEOUT") {
+            Ok(v) => v.parse::<f64>().unwrap(),
+            Err(_) => 10.0,
+        },
+        // Requests are only bounded by the scrape deadline by default.
+        request_timeout: match env::var("EXPORTER_REQUEST_TIMEOUT") {
+            Ok(v) => v.parse::<f64>().unwrap(),
+            Err(_) => 0.0,
+        },
+        http_version: match env::var("EXPORTER_HTTP_VERSION") {
+            Ok(v) => v.parse::<HttpVersion>().unwrap(),
+            Err(_) => HttpVersion::Auto,
+        },
+        tcp_keepalive: match env::var("EXPORTER_TCP_KEEPALIVE") {
+            Ok(v) => v.parse::<u64>().unwrap(),
+            Err(_) => DEFAULT_TCP_KEEPALIVE.as_secs(),
+        },
+        max_response_size: match env::var("EXPORTER_MAX_RESPONSE_SIZE") {
+            Ok(v) => v.parse::<usize>().unwrap(),
+            Err(_) => DEFAULT_MAX_RESPONSE_SIZE,
+        },
+        request_retries: match env::var("EXPORTER_REQUEST_RETRIES") {
+            Ok(v) => v.parse::<u32>().unwrap(),
+            Err(_) => 2,
+        },
+        retry_budget: match env::var("EXPORTER_RETRY_BUDGET") {
+            Ok(v) => v.parse::<u32>().unwrap(),
+            Err(_) => 10,
+        },
+        circuit_breaker_threshold: match env::var("EXPORTER_CIRCUIT_BREAKER_THRESHOLD") {
+            Ok(v) => v.parse::<u32>().unwrap(),
+            Err(_) => 5,
+        },
+        circuit_breaker_cooldown: match env::var("EXPORTER_CIRCUIT_BREAKER_COOLDOWN") {
+            Ok(v) => v.parse::<u64>().unwrap(),
+            Err(_) => 60,
+        },
+        weather_failure_threshold: match env::var("EXPORTER_WEATHER_FAILURE_THRESHOLD") {
+            Ok(v) => v.parse::<u32>().unwrap(),
+            Err(_) => 5,
+        },
+        weather_failure_cooldown: match env::var("EXPORTER_WEATHER_FAILURE_COOLDOWN") {
+            Ok(v) => v.parse::<u64>().unwrap(),
+            Err(_) => 3600,
+        },
+        temperature_filter: match env::var("EXPORTER_TEMPERATURE_FILTER") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        temperature_min: match env::var("EXPORTER_TEMPERATURE_MIN") {
+            Ok(v) => v.parse::<f64>().unwrap(),
+            Err(_) => -50.0,
+        },
+        temperature_max: match env::var("EXPORTER_TEMPERATURE_MAX") {
+            Ok(v) => v.parse::<f64>().unwrap(),
+            Err(_) => 80.0,
+        },
+        humidity_alert: match env::var("EXPORTER_HUMIDITY_ALERT") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        humidity_min: match env::var("EXPORTER_HUMIDITY_MIN") {
+            Ok(v) => v.parse::<f64>().unwrap(),
+            Err(_) => 30.0,
+        },
+        humidity_max: match env::var("EXPORTER_HUMIDITY_MAX") {
+            Ok(v) => v.parse::<f64>().unwrap(),
+            Err(_) => 60.0,
+        },
+        valve_open_threshold: match env::var("EXPORTER_VALVE_OPEN_THRESHOLD") {
+            Ok(v) => v.parse::<f64>().unwrap(),
+            Err(_) => DEFAULT_VALVE_OPEN_THRESHOLD,
+        },
+        valve_close_threshold: match env::var("EXPORTER_VALVE_CLOSE_THRESHOLD") {
+            Ok(v) => v.parse::<f64>().unwrap(),
+            Err(_) => DEFAULT_VALVE_CLOSE_THRESHOLD,
+        },
+        app_temperatures: match env::var("EXPORTER_APP_TEMPERATURES") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        metric_namespace: match env::var("EXPORTER_METRIC_NAMESPACE") {
+            Ok(v) => v,
+            Err(_) => DEFAULT_NAMESPACE.to_string(),
+        },
+        metrics_allow: match env::var("EXPORTER_METRICS_ALLOW") {
+            Ok(v) => v
+                .split(',')
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+        metrics_deny: match env::var("EXPORTER_METRICS_DENY") {
+            Ok(v) => v
+                .split(',')
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+        http_server: match env::var("EXPORTER_HTTP_SERVER") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => true,
+        },
+        base_path: match env::var("EXPORTER_BASE_PATH") {
+            Ok(v) => v,
+            Err(_) => "".to_string(),
+        },
+        metrics_path: match env::var("EXPORTER_METRICS_PATH") {
+            Ok(v) => v,
+            Err(_) => "/metrics".to_string(),
+        },
+        debug_logs: match env::var("EXPORTER_DEBUG_LOGS") {
+            Ok(v) => v.parse::<usize>().unwrap(),
+            Err(_) => 0,
+        },
+        history: match env::var("EXPORTER_HISTORY") {
+            Ok(v) => v.parse::<usize>().unwrap(),
+            Err(_) => 0,
+        },
+        log_requests: match env::var("EXPORTER_LOG_REQUESTS") {
+            Ok(v) => v.parse::<bool>().unwrap(),
+            Err(_) => false,
+        },
+        pushgateway_url: match env::var("EXPORTER_PUSHGATEWAY_URL") {
+            Ok(v) => v,
+            Err(_) => "".to_string(),
+        },
+        pushgateway_job: match env::var("EXPORTER_PUSHGATEWAY_JOB") {
+            Ok(v) => v,
+            Err(_) => "tado".to_string(),
+        },
+        pushgateway_instance: match env::var("EXPORTER_PUSHGATEWAY_INSTANCE") {
+            Ok(v) => v,
+            Err(_) => "".to_string(),
+        },
     };
 
     config.print();
@@ -45,43 +495,328 @@ pub fn load() -> Config {
     config
 }
 
+// Parse a number of seconds scrapes may take, which must be positive: a
+// scrape can't complete within no time at all.
+fn parse_scrape_deadline(value: &str) -> f64 {
+    let seconds = value.parse::<f64>().unwrap();
+    if seconds <= 0.0 || Duration::try_from_secs_f64(seconds).is_err() {
+        panic!(
+            "invalid scrape deadline: {}, expected a positive number of seconds",
+            value
+        );
+    }
+
+    seconds
+}
+
+// Parse a comma separated list of `<zone name>=<value>` settings.
+fn parse_zone_settings(value: &str) -> HashMap<String, String> {
+    value
+        .split(',')
+        .filter(|setting| !setting.trim().is_empty())
+        .map(|setting| {
+            let (zone, value) = setting
+                .rsplit_once('=')
+                .unwrap_or_else(|| panic!("invalid zone setting: {}", setting));
+            (zone.trim().to_string(), value.trim().to_string())
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use rstest::*;
+
     #[test]
     fn test_config_load() {
         // Given no env variable are set
         env::remove_var("EXPORTER_USERNAME");
         env::remove_var("EXPORTER_PASSWORD");
+        env::remove_var("EXPORTER_PASSWORD_FILE");
+        env::remove_var("EXPORTER_SECONDARY_USERNAME");
+        env::remove_var("EXPORTER_SECONDARY_PASSWORD");
+        env::remove_var("EXPORTER_CREDENTIALS_REFRESH");
+        env::remove_var("EXPORTER_CACHE_FILE");
+        env::remove_var("EXPORTER_TOKEN_FILE");
+        env::remove_var("EXPORTER_AUTH_FAIL_OPEN");
+        env::remove_var("EXPORTER_BACKGROUND_RENEWAL");
         env::remove_var("EXPORTER_TICKER");
+        env::remove_var("EXPORTER_CRON");
+        env::remove_var("EXPORTER_ZONE_INTERVALS");
+        env::remove_var("EXPORTER_ZONE_GROUPS");
+        env::remove_var("EXPORTER_ZONE_IDS");
+        env::remove_var("EXPORTER_ZONE_EVICTION_GRACE");
+        env::remove_var("EXPORTER_ZONE_LABEL");
         env::remove_var("EXPORTER_CLIENT_SECRET");
+        env::remove_var("EXPORTER_CLIENT_IDS");
+        env::remove_var("EXPORTER_SKIP_STALE_SAMPLES");
+        env::remove_var("EXPORTER_HOLD_ON_FAILURE");
+        env::remove_var("EXPORTER_ENDPOINT_WEATHER");
+        env::remove_var("EXPORTER_SCRAPE_DEADLINE");
+        env::remove_var("EXPORTER_COLLECT_SCHEDULES");
+        env::remove_var("EXPORTER_COLLECT_DEVICES");
+        env::remove_var("EXPORTER_COLLECT_MOBILE_DEVICES");
+        env::remove_var("EXPORTER_COLLECT_HOME_STATE");
+        env::remove_var("EXPORTER_HOME_LOCATION");
+        env::remove_var("EXPORTER_CONNECT_TIMEOUT");
+        env::remove_var("EXPORTER_REQUEST_TIMEOUT");
+        env::remove_var("EXPORTER_HTTP_VERSION");
+        env::remove_var("EXPORTER_TCP_KEEPALIVE");
+        env::remove_var("EXPORTER_MAX_RESPONSE_SIZE");
+        env::remove_var("EXPORTER_REQUEST_RETRIES");
+        env::remove_var("EXPORTER_RETRY_BUDGET");
+        env::remove_var("EXPORTER_CIRCUIT_BREAKER_THRESHOLD");
+        env::remove_var("EXPORTER_CIRCUIT_BREAKER_COOLDOWN");
+        env::remove_var("EXPORTER_WEATHER_FAILURE_THRESHOLD");
+        env::remove_var("EXPORTER_WEATHER_FAILURE_COOLDOWN");
+        env::remove_var("EXPORTER_TEMPERATURE_FILTER");
+        env::remove_var("EXPORTER_TEMPERATURE_MIN");
+        env::remove_var("EXPORTER_TEMPERATURE_MAX");
+        env::remove_var("EXPORTER_HUMIDITY_ALERT");
+        env::remove_var("EXPORTER_HUMIDITY_MIN");
+        env::remove_var("EXPORTER_HUMIDITY_MAX");
+        env::remove_var("EXPORTER_VALVE_OPEN_THRESHOLD");
+        env::remove_var("EXPORTER_VALVE_CLOSE_THRESHOLD");
+        env::remove_var("EXPORTER_APP_TEMPERATURES");
+        env::remove_var("EXPORTER_METRIC_NAMESPACE");
+        env::remove_var("EXPORTER_METRICS_ALLOW");
+        env::remove_var("EXPORTER_METRICS_DENY");
+        env::remove_var("EXPORTER_HTTP_SERVER");
+        env::remove_var("EXPORTER_BASE_PATH");
+        env::remove_var("EXPORTER_METRICS_PATH");
+        env::remove_var("EXPORTER_DEBUG_LOGS");
+        env::remove_var("EXPORTER_HISTORY");
+        env::remove_var("EXPORTER_LOG_REQUESTS");
+        env::remove_var("EXPORTER_PUSHGATEWAY_URL");
+        env::remove_var("EXPORTER_PUSHGATEWAY_JOB");
+        env::remove_var("EXPORTER_PUSHGATEWAY_INSTANCE");
 
         // when
         let config = load();
 
         // then we should load default values
         assert_eq!(config.ticker, 10);
+        assert_eq!(config.cron, "");
+        assert!(config.zone_intervals.is_empty());
+        assert!(config.zone_groups.is_empty());
+        assert!(config.zone_ids.is_empty());
+        assert_eq!(config.zone_eviction_grace, 3600);
+        assert_eq!(config.zone_label, ZoneLabel::Name);
         assert_eq!(config.username, "");
         assert_eq!(config.password, "");
+        assert_eq!(config.password_file, "");
+        assert_eq!(config.secondary_username, "");
+        assert_eq!(config.secondary_password, "");
+        assert_eq!(config.credentials_refresh, 300);
+        assert_eq!(config.cache_file, "");
+        assert_eq!(config.token_file, "");
+        assert!(config.auth_fail_open);
+        assert!(config.background_renewal);
         assert_eq!(
             config.client_secret,
             "wZaRN7rpjn3FoNyF5IFuxg9uMzYJcvOoQ8QWiIqS3hfk6gLhVlG57j5YNoZL2Rtc"
         );
+        assert_eq!(config.client_ids, vec!["tado-web-app"]);
+        assert!(!config.skip_stale_samples);
+        assert!(!config.hold_on_failure);
+        assert_eq!(config.endpoints, Endpoints::default());
+        assert_eq!(config.scrape_deadline, None);
+        assert!(!config.collect_schedules);
+        assert!(!config.collect_devices);
+        assert!(!config.collect_mobile_devices);
+        assert!(!config.collect_home_state);
+        assert!(!config.home_location);
+        assert_eq!(config.connect_timeout, 10.0);
+        assert_eq!(config.request_timeout, 0.0);
+        assert_eq!(config.http_version, HttpVersion::Auto);
+        assert_eq!(config.tcp_keepalive, 60);
+        assert_eq!(config.max_response_size, 10485760);
+        assert_eq!(config.request_retries, 2);
+        assert_eq!(config.retry_budget, 10);
+        assert_eq!(config.circuit_breaker_threshold, 5);
+        assert_eq!(config.circuit_breaker_cooldown, 60);
+        assert_eq!(config.weather_failure_threshold, 5);
+        assert_eq!(config.weather_failure_cooldown, 3600);
+        assert!(!config.temperature_filter);
+        assert_eq!(config.temperature_min, -50.0);
+        assert_eq!(config.temperature_max, 80.0);
+        assert!(!config.humidity_alert);
+        assert_eq!(config.humidity_min, 30.0);
+        assert_eq!(config.humidity_max, 60.0);
+        assert_eq!(config.valve_open_threshold, 5.0);
+        assert_eq!(config.valve_close_threshold, 1.0);
+        assert!(!config.app_temperatures);
+        assert_eq!(config.metric_namespace, "tado");
+        assert!(config.metrics_allow.is_empty());
+        assert!(config.metrics_deny.is_empty());
+        assert!(config.http_server);
+        assert_eq!(config.base_path, "");
+        assert_eq!(config.metrics_path, "/metrics");
+        assert_eq!(config.debug_logs, 0);
+        assert_eq!(config.history, 0);
+        assert!(!config.log_requests);
+        assert_eq!(config.pushgateway_url, "");
+        assert_eq!(config.pushgateway_job, "tado");
+        assert_eq!(config.pushgateway_instance, "");
 
         // given the following environment variable values
         env::set_var("EXPORTER_USERNAME", "test-user");
         env::set_var("EXPORTER_PASSWORD", "123Password!");
+        env::set_var("EXPORTER_PASSWORD_FILE", "/run/secrets/tado-password");
+        env::set_var("EXPORTER_SECONDARY_USERNAME", "family-user");
+        env::set_var("EXPORTER_SECONDARY_PASSWORD", "456Password!");
+        env::set_var("EXPORTER_CREDENTIALS_REFRESH", "3600");
+        env::set_var("EXPORTER_CACHE_FILE", "/var/cache/tado-exporter.json");
+        env::set_var("EXPORTER_TOKEN_FILE", "/var/lib/tado-exporter/token.json");
+        env::set_var("EXPORTER_AUTH_FAIL_OPEN", "false");
+        env::set_var("EXPORTER_BACKGROUND_RENEWAL", "false");
         env::set_var("EXPORTER_TICKER", "30");
+        env::set_var("EXPORTER_CRON", "*/5 8-19 * * *");
+        env::set_var(
+            "EXPORTER_ZONE_INTERVALS",
+            "Server closet=60, Guest room = 900",
+        );
+        env::set_var(
+            "EXPORTER_ZONE_GROUPS",
+            "Kitchen=Open space,Living room=Open space",
+        );
+        env::set_var("EXPORTER_ZONE_IDS", "1, 4");
+        env::set_var("EXPORTER_ZONE_EVICTION_GRACE", "0");
+        env::set_var("EXPORTER_ZONE_LABEL", "id");
         env::set_var("EXPORTER_CLIENT_SECRET", "123-secret");
+        env::set_var("EXPORTER_CLIENT_IDS", "rotated-app, tado-web-app");
+        env::set_var("EXPORTER_SKIP_STALE_SAMPLES", "true");
+        env::set_var("EXPORTER_HOLD_ON_FAILURE", "true");
+        env::set_var("EXPORTER_ENDPOINT_WEATHER", "/proxy/weather/{home_id}");
+        env::set_var("EXPORTER_SCRAPE_DEADLINE", "12.5");
+        env::set_var("EXPORTER_COLLECT_SCHEDULES", "true");
+        env::set_var("EXPORTER_COLLECT_DEVICES", "true");
+        env::set_var("EXPORTER_COLLECT_MOBILE_DEVICES", "true");
+        env::set_var("EXPORTER_COLLECT_HOME_STATE", "true");
+        env::set_var("EXPORTER_HOME_LOCATION", "true");
+        env::set_var("EXPORTER_CONNECT_TIMEOUT", "2.5");
+        env::set_var("EXPORTER_REQUEST_TIMEOUT", "30");
+        env::set_var("EXPORTER_HTTP_VERSION", "http1");
+        env::set_var("EXPORTER_TCP_KEEPALIVE", "0");
+        env::set_var("EXPORTER_MAX_RESPONSE_SIZE", "1048576");
+        env::set_var("EXPORTER_REQUEST_RETRIES", "0");
+        env::set_var("EXPORTER_RETRY_BUDGET", "25");
+        env::set_var("EXPORTER_CIRCUIT_BREAKER_THRESHOLD", "0");
+        env::set_var("EXPORTER_CIRCUIT_BREAKER_COOLDOWN", "300");
+        env::set_var("EXPORTER_WEATHER_FAILURE_THRESHOLD", "3");
+        env::set_var("EXPORTER_WEATHER_FAILURE_COOLDOWN", "600");
+        env::set_var("EXPORTER_TEMPERATURE_FILTER", "true");
+        env::set_var("EXPORTER_TEMPERATURE_MIN", "5");
+        env::set_var("EXPORTER_TEMPERATURE_MAX", "40.5");
+        env::set_var("EXPORTER_HUMIDITY_ALERT", "true");
+        env::set_var("EXPORTER_HUMIDITY_MIN", "40");
+        env::set_var("EXPORTER_HUMIDITY_MAX", "65.5");
+        env::set_var("EXPORTER_VALVE_OPEN_THRESHOLD", "10");
+        env::set_var("EXPORTER_VALVE_CLOSE_THRESHOLD", "2.5");
+        env::set_var("EXPORTER_APP_TEMPERATURES", "true");
+        env::set_var("EXPORTER_METRIC_NAMESPACE", "home");
+        env::set_var("EXPORTER_METRICS_ALLOW", "tado_sensor_*, weather_*");
+        env::set_var("EXPORTER_METRICS_DENY", "tado_sensor_humidity_percentage");
+        env::set_var("EXPORTER_HTTP_SERVER", "false");
+        env::set_var("EXPORTER_BASE_PATH", "/tado");
+        env::set_var("EXPORTER_METRICS_PATH", "/prometheus");
+        env::set_var("EXPORTER_DEBUG_LOGS", "500");
+        env::set_var("EXPORTER_HISTORY", "360");
+        env::set_var("EXPORTER_LOG_REQUESTS", "true");
+        env::set_var("EXPORTER_PUSHGATEWAY_URL", "http://localhost:9091");
+        env::set_var("EXPORTER_PUSHGATEWAY_JOB", "tado-cron");
+        env::set_var("EXPORTER_PUSHGATEWAY_INSTANCE", "home");
 
         // when
         let config = load();
 
         // then we should have these values set
         assert_eq!(config.ticker, 30);
+        assert_eq!(config.cron, "*/5 8-19 * * *");
+        assert_eq!(
+            config.zone_intervals,
+            HashMap::from([
+                ("Server closet".to_string(), 60),
+                ("Guest room".to_string(), 900)
+            ])
+        );
+        assert_eq!(
+            config.zone_groups,
+            HashMap::from([
+                ("Kitchen".to_string(), "Open space".to_string()),
+                ("Living room".to_string(), "Open space".to_string())
+            ])
+        );
+        assert_eq!(config.zone_ids, vec![1, 4]);
+        assert_eq!(config.zone_eviction_grace, 0);
+        assert_eq!(config.zone_label, ZoneLabel::Id);
         assert_eq!(config.username, "test-user");
         assert_eq!(config.password, "123Password!");
+        assert_eq!(config.password_file, "/run/secrets/tado-password");
+        assert_eq!(config.secondary_username, "family-user");
+        assert_eq!(config.secondary_password, "456Password!");
+        assert_eq!(config.credentials_refresh, 3600);
+        assert_eq!(config.cache_file, "/var/cache/tado-exporter.json");
+        assert_eq!(config.token_file, "/var/lib/tado-exporter/token.json");
+        assert!(!config.auth_fail_open);
+        assert!(!config.background_renewal);
         assert_eq!(config.client_secret, "123-secret");
+        assert_eq!(config.client_ids, vec!["rotated-app", "tado-web-app"]);
+        assert!(config.skip_stale_samples);
+        assert!(config.hold_on_failure);
+        assert_eq!(config.endpoints.weather, "/proxy/weather/{home_id}");
+        assert_eq!(config.endpoints.zones, "/api/v2/homes/{home_id}/zones");
+        assert_eq!(config.scrape_deadline, Some(12.5));
+        assert!(config.collect_schedules);
+        assert!(config.collect_devices);
+        assert!(config.collect_mobile_devices);
+        assert!(config.collect_home_state);
+        assert!(config.home_location);
+        assert_eq!(config.connect_timeout, 2.5);
+        assert_eq!(config.request_timeout, 30.0);
+        assert_eq!(config.http_version, HttpVersion::Http1);
+        assert_eq!(config.tcp_keepalive, 0);
+        assert_eq!(config.max_response_size, 1048576);
+        assert_eq!(config.request_retries, 0);
+        assert_eq!(config.retry_budget, 25);
+        assert_eq!(config.circuit_breaker_threshold, 0);
+        assert_eq!(config.circuit_breaker_cooldown, 300);
+        assert_eq!(config.weather_failure_threshold, 3);
+        assert_eq!(config.weather_failure_cooldown, 600);
+        assert!(config.temperature_filter);
+        assert_eq!(config.temperature_min, 5.0);
+        assert_eq!(config.temperature_max, 40.5);
+        assert!(config.humidity_alert);
+        assert_eq!(config.humidity_min, 40.0);
+        assert_eq!(config.humidity_max, 65.5);
+        assert_eq!(config.valve_open_threshold, 10.0);
+        assert_eq!(config.valve_close_threshold, 2.5);
+        assert!(config.app_temperatures);
+        assert_eq!(config.metric_namespace, "home");
+        assert_eq!(config.metrics_allow, vec!["tado_sensor_*", "weather_*"]);
+        assert_eq!(config.metrics_deny, vec!["tado_sensor_humidity_percentage"]);
+        assert!(!config.http_server);
+        assert_eq!(config.base_path, "/tado");
+        assert_eq!(config.metrics_path, "/prometheus");
+        assert_eq!(config.debug_logs, 500);
+        assert_eq!(config.history, 360);
+        assert!(config.log_requests);
+        assert_eq!(config.pushgateway_url, "http://localhost:9091");
+        assert_eq!(config.pushgateway_job, "tado-cron");
+        assert_eq!(config.pushgateway_instance, "home");
+    }
+
+    #[rstest(value, case("0"), case("-1"), case("NaN"), case("inf"))]
+    #[should_panic(expected = "invalid scrape deadline")]
+    fn test_parse_scrape_deadline_invalid(value: &str) {
+        /*
+        GIVEN a scrape deadline that isn't a positive number of seconds
+        WHEN parsing it
+        THEN the configuration is rejected
+        */
+
+        parse_scrape_deadline(value);
     }
 }
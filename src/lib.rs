@@ -0,0 +1,5 @@
+#[macro_use]
+extern crate prometheus;
+
+pub mod config;
+pub mod tado;
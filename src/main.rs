@@ -1,34 +1,129 @@
-#[macro_use]
-extern crate prometheus;
-
-mod config;
-mod tado;
-
+use chrono::NaiveDate;
 use env_logger::{Builder as LoggerBuilder, Env};
 use hyper::{service::make_service_fn, service::service_fn, Server};
 use log::{error, info};
 use std::convert::Infallible;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
-use config::loader as config_loader;
-use tado::client::Client as TadoClient;
-use tado::metrics;
+use tado_exporter::config::loader as config_loader;
+use tado_exporter::tado::backfill;
+use tado_exporter::tado::breaker::CircuitBreaker;
+use tado_exporter::tado::client::{Client as TadoClient, ClientError};
+use tado_exporter::tado::collector::Collector;
+use tado_exporter::tado::credentials::{Credentials, FileCredentials};
+use tado_exporter::tado::debug;
+use tado_exporter::tado::history;
+use tado_exporter::tado::logs;
+use tado_exporter::tado::metrics::{self, MetricFilter};
+use tado_exporter::tado::pushgateway::Pusher;
+use tado_exporter::tado::router::{self, Routes};
+use tado_exporter::tado::ticker::Ticker;
 
 #[tokio::main]
 async fn main() {
-    LoggerBuilder::from_env(Env::default().default_filter_or("info")).init();
-
     let config = config_loader::load();
 
+    if let Err(e) = logs::init(
+        LoggerBuilder::from_env(Env::default().default_filter_or("info")),
+        config.debug_logs,
+    ) {
+        eprintln!("unable to set up logging: {}", e);
+        std::process::exit(1);
+    }
+
+    // help writing the per-zone settings, rather than exporting metrics
+    if std::env::args().any(|arg| arg == "--list-zones") {
+        if let Err(e) = list_zones(config).await {
+            error!("unable to list zones: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // print the measurements of a past day, rather than exporting metrics
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(position) = args.iter().position(|arg| arg == "--backfill") {
+        let date = match args.get(position + 1).map(|date| date.parse::<NaiveDate>()) {
+            Some(Ok(date)) => date,
+            _ => {
+                error!("--backfill expects a date, e.g. --backfill 2024-01-14");
+                std::process::exit(1);
+            }
+        };
+
+        if let Err(e) = backfill(config, date).await {
+            error!("unable to backfill {}: {}", date, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(e) = metrics::set_namespace(&config.metric_namespace) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+
+    metrics::set_metric_filter(MetricFilter {
+        allow: config.metrics_allow.clone(),
+        deny: config.metrics_deny.clone(),
+    });
+
+    metrics::set_build_info();
+    history::init(config.history);
+
+    let cron_ticker = if config.cron.is_empty() {
+        None
+    } else {
+        match Ticker::cron(&config.cron) {
+            Ok(ticker) => Some(ticker),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let http_server = config.http_server;
+    let routes = Arc::new(Routes::new(&config.base_path, &config.metrics_path));
+
+    let mut tado_client = scrape_client(&config);
+    if let Err(e) = tado_client
+        .startup_authenticate(config.auth_fail_open)
+        .await
+    {
+        error!("unable to authenticate: {}", e);
+        std::process::exit(1);
+    }
+
     // start ticker
-    run_ticker(config);
+    let ticker = run_ticker(config, tado_client, cron_ticker);
+
+    // metrics are only pushed to the pushgateway, keep running the ticker
+    if !http_server {
+        if let Err(e) = ticker.await {
+            error!("the ticker stopped: {}", e);
+        }
+        return;
+    }
 
     // set up http server
     let addr = ([0, 0, 0, 0], 9898).into();
-    info!("starting tado° exporter on address: {:?}", addr);
+    info!(
+        "starting tado° exporter on address: {:?}, serving metrics at {}",
+        addr, routes.metrics
+    );
 
-    let make_svc =
-        make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(metrics::renderer)) });
+    let make_svc = make_service_fn(move |_conn| {
+        let routes = routes.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let routes = routes.clone();
+                async move { router::route(&routes, req).await }
+            }))
+        }
+    });
 
     let server = Server::bind(&addr).serve(make_svc);
 
@@ -38,22 +133,247 @@ async fn main() {
     }
 }
 
-fn run_ticker(config: config_loader::Config) {
+async fn list_zones(config: config_loader::Config) -> Result<(), ClientError> {
+    let mut tado_client = with_password_file(
+        TadoClient::new(
+            config.username.clone(),
+            config.password.clone(),
+            config.client_secret.clone(),
+        ),
+        &config,
+    )
+    .with_endpoints(config.endpoints)
+    .with_client_ids(config.client_ids)
+    .with_request_logging(config.log_requests);
+
+    for home in tado_client.list_homes().await? {
+        println!("Home {} ({})", home.id, home.name);
+        println!("  {:<6} {:<24} {:<18} Devices", "Id", "Zone", "Type");
+
+        for zone in tado_client.list_zones(home.id).await? {
+            let devices: Vec<String> = zone
+                .devices
+                .iter()
+                .map(|device| format!("{} ({})", device.serialNo, device.deviceType))
+                .collect();
+
+            println!(
+                "  {:<6} {:<24} {:<18} {}",
+                zone.id,
+                zone.name,
+                zone.zoneType,
+                devices.join(", ")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Print the day reports of the scraped zones in the OpenMetrics format, to
+// import into a time-series database.
+async fn backfill(config: config_loader::Config, date: NaiveDate) -> Result<(), ClientError> {
+    let mut tado_client = with_password_file(
+        TadoClient::new(
+            config.username.clone(),
+            config.password.clone(),
+            config.client_secret.clone(),
+        ),
+        &config,
+    )
+    .with_endpoints(config.endpoints.clone())
+    .with_client_ids(config.client_ids.clone())
+    .with_request_logging(config.log_requests)
+    .with_max_response_size(config.max_response_size)
+    .with_zone_ids(config.zone_ids.clone())
+    .with_zone_label(config.zone_label);
+
+    let mut samples = Vec::new();
+    for (zone, day_report) in tado_client.day_reports(date).await? {
+        samples.extend(backfill::samples(&zone, &day_report));
+    }
+
+    print!("{}", backfill::render(&samples, &config.metric_namespace));
+
+    Ok(())
+}
+
+// Read the password from EXPORTER_PASSWORD_FILE, if set, rather than from
+// EXPORTER_PASSWORD, so rotated secrets are picked up. Also fail over to the
+// secondary credentials, if set.
+fn with_password_file(tado_client: TadoClient, config: &config_loader::Config) -> TadoClient {
+    let tado_client = if config.secondary_username.is_empty() {
+        tado_client
+    } else {
+        tado_client.with_secondary_credentials(Credentials {
+            username: config.secondary_username.clone(),
+            password: config.secondary_password.clone(),
+        })
+    };
+
+    if config.password_file.is_empty() {
+        return tado_client;
+    }
+
+    tado_client.with_credential_provider(
+        Arc::new(FileCredentials::new(
+            config.username.clone(),
+            PathBuf::from(&config.password_file),
+        )),
+        Duration::from_secs(config.credentials_refresh),
+    )
+}
+
+fn scrape_client(config: &config_loader::Config) -> TadoClient {
+    let tado_client = with_password_file(
+        TadoClient::new(
+            config.username.clone(),
+            config.password.clone(),
+            config.client_secret.clone(),
+        ),
+        config,
+    )
+    .with_endpoints(config.endpoints.clone())
+    .with_client_ids(config.client_ids.clone())
+    .with_request_logging(config.log_requests)
+    .with_max_response_size(config.max_response_size)
+    .with_connect_timeout(Duration::from_secs_f64(config.connect_timeout))
+    .with_http_version(config.http_version)
+    .with_tcp_keepalive(
+        Some(Duration::from_secs(config.tcp_keepalive)).filter(|keepalive| !keepalive.is_zero()),
+    )
+    .with_schedules(config.collect_schedules)
+    .with_devices(config.collect_devices)
+    .with_mobile_devices(config.collect_mobile_devices)
+    .with_home_state(config.collect_home_state)
+    .with_home_location(config.home_location)
+    .with_retries(config.request_retries, config.retry_budget)
+    .with_weather_failures(
+        config.weather_failure_threshold,
+        Duration::from_secs(config.weather_failure_cooldown),
+    )
+    .with_zone_ids(config.zone_ids.clone())
+    .with_zone_label(config.zone_label)
+    .with_zone_intervals(
+        Duration::from_secs(config.ticker),
+        config
+            .zone_intervals
+            .iter()
+            .map(|(zone, seconds)| (zone.clone(), Duration::from_secs(*seconds)))
+            .collect(),
+    );
+
+    let tado_client = if config.cache_file.is_empty() {
+        tado_client
+    } else {
+        tado_client.with_cache_file(PathBuf::from(&config.cache_file))
+    };
+
+    let tado_client = if config.token_file.is_empty() {
+        tado_client
+    } else {
+        tado_client.with_token_store(PathBuf::from(&config.token_file))
+    };
+
+    if config.request_timeout > 0.0 {
+        tado_client.with_timeout(Duration::from_secs_f64(config.request_timeout))
+    } else {
+        tado_client
+    }
+}
+
+fn run_ticker(
+    config: config_loader::Config,
+    tado_client: TadoClient,
+    cron_ticker: Option<Ticker>,
+) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
-        let mut tado_client =
-            TadoClient::new(config.username, config.password, config.client_secret);
+        // Tick as often as the zone with the shortest interval needs it, the
+        // client only retrieves the zones that are due.
+        let tick = config
+            .zone_intervals
+            .values()
+            .copied()
+            .filter(|seconds| *seconds > 0)
+            .fold(config.ticker, u64::min);
 
-        info!("waiting for the first tick in {} seconds...", config.ticker);
+        let mut ticker = match cron_ticker {
+            Some(ticker) => {
+                info!("waiting for the first tick of {}...", config.cron);
+                ticker
+            }
+            None => {
+                info!("waiting for the first tick in {} seconds...", tick);
+                Ticker::interval(Duration::from_secs(tick))
+            }
+        };
 
-        // Use a ticker instead of sleeping within the loop.
-        // This prevents drift as the ticker keeps counting down during refresh, unlike sleep.
-        let mut ticker = tokio::time::interval(Duration::from_secs(config.ticker));
-        ticker.tick().await;
+        // Leave some headroom before the next tick by default, so scrapes
+        // never pile up.
+        let deadline = match config.scrape_deadline {
+            Some(seconds) => Some(Duration::from_secs_f64(seconds)),
+            None => ticker.shortest_period().map(|period| period.mul_f64(0.8)),
+        };
+
+        let mut collector = Collector::new(config.skip_stale_samples)
+            .with_scrape_interval(Duration::from_secs(tick))
+            .with_zone_groups(config.zone_groups.clone())
+            .with_hold_on_failure(config.hold_on_failure)
+            .with_app_temperatures(config.app_temperatures)
+            .with_valve_thresholds(config.valve_open_threshold, config.valve_close_threshold)
+            .with_zone_eviction_grace(Duration::from_secs(config.zone_eviction_grace));
+        if let Some(deadline) = deadline {
+            collector = collector.with_deadline(deadline);
+        }
+        if config.temperature_filter {
+            collector =
+                collector.with_temperature_bounds(config.temperature_min, config.temperature_max);
+        }
+        if config.humidity_alert {
+            collector = collector.with_humidity_band(config.humidity_min, config.humidity_max);
+        }
+        if config.circuit_breaker_threshold > 0 {
+            collector = collector.with_circuit_breaker(CircuitBreaker::new(
+                config.circuit_breaker_threshold,
+                Duration::from_secs(config.circuit_breaker_cooldown),
+            ));
+        }
+
+        let pusher = if config.pushgateway_url.is_empty() {
+            None
+        } else {
+            match Pusher::new(
+                &config.pushgateway_url,
+                &config.pushgateway_job,
+                &config.pushgateway_instance,
+            ) {
+                Ok(pusher) => Some(pusher),
+                Err(e) => {
+                    error!("not pushing metrics: {}", e);
+                    None
+                }
+            }
+        };
+
+        // renew the access token between scrapes, so they don't wait for the
+        // authentication
+        let tado_client = Arc::new(tokio::sync::Mutex::new(tado_client));
+        if config.background_renewal {
+            TadoClient::spawn_refresh_task(tado_client.clone());
+        }
 
         loop {
             ticker.tick().await;
-            metrics::set_zones(tado_client.retrieve_zones().await);
-            metrics::set_weather(tado_client.retrieve_weather().await);
+
+            let mut client = tado_client.lock().await;
+            collector.collect(&mut *client).await;
+            debug::set_client_state(client.debug_state());
+            drop(client);
+
+            if let Some(pusher) = &pusher {
+                // failures are logged by the pusher, the next tick pushes again
+                let _ = pusher.push().await;
+            }
         }
-    });
+    })
 }
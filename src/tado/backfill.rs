@@ -0,0 +1,287 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use log::warn;
+
+use super::metrics::DEFAULT_NAMESPACE;
+use super::model::DayReportApiResponse;
+
+/// A value measured in a zone at some point in the past, named and labelled
+/// like the live metric it backfills.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub metric: &'static str,
+    // sorted by name, as rendered
+    pub labels: Vec<(&'static str, String)>,
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// Turn the day report of `zone` into samples: the inside temperature and
+/// humidity of each data point, and the temperature set at the start of each
+/// setting interval and at the data points within it, so settings are sampled
+/// as often as the measurements. Data points with an unreadable timestamp are
+/// skipped.
+pub fn samples(zone: &str, report: &DayReportApiResponse) -> Vec<Sample> {
+    let zone_type = report.zoneType.as_str();
+    let mut samples = Vec::new();
+    let mut measured_at = Vec::new();
+
+    for data_point in report.measuredData.insideTemperature.dataPoints.iter() {
+        if let Some(timestamp) = parse_timestamp(zone, &data_point.timestamp) {
+            measured_at.push(timestamp);
+            for (unit, value) in [
+                ("celsius", data_point.value.celsius),
+                ("fahrenheit", data_point.value.fahrenheit),
+            ] {
+                samples.push(Sample {
+                    metric: "tado_sensor_temperature_value",
+                    labels: vec![
+                        ("type", zone_type.to_string()),
+                        ("unit", unit.to_string()),
+                        ("zone", zone.to_string()),
+                    ],
+                    timestamp,
+                    value,
+                });
+            }
+        }
+    }
+
+    for data_point in report.measuredData.humidity.dataPoints.iter() {
+        if let Some(timestamp) = parse_timestamp(zone, &data_point.timestamp) {
+            samples.push(Sample {
+                metric: "tado_sensor_humidity_percentage",
+                labels: vec![("type", zone_type.to_string()), ("zone", zone.to_string())],
+                timestamp,
+                value: data_point.value * 100.0,
+            });
+        }
+    }
+
+    for interval in report.settings.dataIntervals.iter() {
+        // zones switched off set no temperature
+        let temperature = interval
+            .value
+            .as_ref()
+            .filter(|setting| setting.is_powered_on())
+            .and_then(|setting| setting.temperature.as_ref());
+        let from = parse_timestamp(zone, &interval.from);
+        let to = parse_timestamp(zone, &interval.to);
+
+        if let (Some(temperature), Some(from), Some(to)) = (temperature, from, to) {
+            let timestamps = std::iter::once(from).chain(
+                measured_at
+                    .iter()
+                    .copied()
+                    .filter(|timestamp| from < *timestamp && *timestamp < to),
+            );
+            for timestamp in timestamps {
+                for (unit, value) in [
+                    ("celsius", temperature.celsius),
+                    ("fahrenheit", temperature.fahrenheit),
+                ] {
+                    samples.push(Sample {
+                        metric: "tado_setting_temperature_value",
+                        labels: vec![
+                            ("type", zone_type.to_string()),
+                            ("unit", unit.to_string()),
+                            ("zone", zone.to_string()),
+                        ],
+                        timestamp,
+                        value,
+                    });
+                }
+            }
+        }
+    }
+
+    samples
+}
+
+/// Render samples in the OpenMetrics text format, with the metric names in
+/// `namespace`, e.g. for `promtool tsdb create-blocks-from openmetrics`.
+/// Samples are grouped by metric and series, oldest first, as required.
+pub fn render(samples: &[Sample], namespace: &str) -> String {
+    let mut samples: Vec<&Sample> = samples.iter().collect();
+    samples.sort_by(|a, b| {
+        (a.metric, &a.labels, a.timestamp).cmp(&(b.metric, &b.labels, b.timestamp))
+    });
+
+    let mut output = String::new();
+    let mut family = None;
+    for sample in samples {
+        let name = namespaced(sample.metric, namespace);
+        if family != Some(sample.metric) {
+            writeln!(output, "# TYPE {} gauge", name).unwrap();
+            family = Some(sample.metric);
+        }
+
+        let labels: Vec<String> = sample
+            .labels
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+            .collect();
+        writeln!(
+            output,
+            "{}{{{}}} {} {}",
+            name,
+            labels.join(","),
+            sample.value,
+            sample.timestamp.timestamp_millis() as f64 / 1000.0
+        )
+        .unwrap();
+    }
+    output.push_str("# EOF\n");
+
+    output
+}
+
+fn parse_timestamp(zone: &str, timestamp: &str) -> Option<DateTime<Utc>> {
+    match DateTime::parse_from_rfc3339(timestamp) {
+        Ok(timestamp) => Some(timestamp.with_timezone(&Utc)),
+        Err(e) => {
+            warn!(
+                "-> {} -> skipping data point with invalid timestamp {}: {}",
+                zone, timestamp, e
+            );
+            None
+        }
+    }
+}
+
+fn namespaced(metric: &str, namespace: &str) -> String {
+    let name = metric
+        .strip_prefix(DEFAULT_NAMESPACE)
+        .and_then(|name| name.strip_prefix('_'))
+        .unwrap_or(metric);
+
+    if namespace.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}_{}", namespace, name)
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_REPORT: &str = r#"{
+        "zoneType": "HEATING",
+        "interval": {
+            "from": "2024-01-13T23:45:00.000Z",
+            "to": "2024-01-15T00:15:00.000Z"
+        },
+        "hoursInDay": 24,
+        "measuredData": {
+            "measuringDeviceConnected": {
+                "timeSeriesType": "dataIntervals",
+                "valueType": "boolean",
+                "dataIntervals": [
+                    { "from": "2024-01-13T23:45:00.000Z", "to": "2024-01-15T00:15:00.000Z", "value": true }
+                ]
+            },
+            "insideTemperature": {
+                "timeSeriesType": "dataPoints",
+                "valueType": "temperature",
+                "min": { "celsius": 19.5, "fahrenheit": 67.1 },
+                "max": { "celsius": 20.1, "fahrenheit": 68.18 },
+                "dataPoints": [
+                    { "timestamp": "2024-01-14T00:00:00.000Z", "value": { "celsius": 20.1, "fahrenheit": 68.18 } },
+                    { "timestamp": "2024-01-14T00:15:00.000Z", "value": { "celsius": 19.5, "fahrenheit": 67.1 } }
+                ]
+            },
+            "humidity": {
+                "timeSeriesType": "dataPoints",
+                "valueType": "percentage",
+                "percentageUnit": "UNIT_INTERVAL",
+                "min": 0.512,
+                "max": 0.525,
+                "dataPoints": [
+                    { "timestamp": "2024-01-14T00:00:00.000Z", "value": 0.525 },
+                    { "timestamp": "2024-01-14T00:15:00.000Z", "value": 0.512 }
+                ]
+            }
+        },
+        "settings": {
+            "timeSeriesType": "dataIntervals",
+            "valueType": "heatingSetting",
+            "dataIntervals": [
+                {
+                    "from": "2024-01-13T23:45:00.000Z",
+                    "to": "2024-01-14T00:10:00.000Z",
+                    "value": { "type": "HEATING", "power": "ON", "temperature": { "celsius": 20.0, "fahrenheit": 68.0 } }
+                },
+                {
+                    "from": "2024-01-14T00:10:00.000Z",
+                    "to": "2024-01-15T00:15:00.000Z",
+                    "value": { "type": "HEATING", "power": "OFF", "temperature": null }
+                }
+            ]
+        },
+        "callForHeat": {
+            "timeSeriesType": "dataIntervals",
+            "valueType": "callForHeat",
+            "dataIntervals": [
+                { "from": "2024-01-13T23:45:00.000Z", "to": "2024-01-15T00:15:00.000Z", "value": "NONE" }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_day_report_samples() {
+        /*
+        GIVEN the day report of a zone, switched off after a while
+        WHEN turning it into samples and rendering them
+        THEN every temperature and humidity data point is a sample, the
+             setting is sampled along with them while the zone is on, and
+             they are rendered grouped by series in the OpenMetrics format
+        */
+
+        // GIVEN
+        let report: DayReportApiResponse = serde_json::from_str(DAY_REPORT).unwrap();
+
+        // WHEN
+        let samples = samples("Office", &report);
+        let output = render(&samples, DEFAULT_NAMESPACE);
+
+        // THEN
+        assert_eq!(samples.len(), 10);
+        assert!(samples.contains(&Sample {
+            metric: "tado_sensor_humidity_percentage",
+            labels: vec![
+                ("type", "HEATING".to_string()),
+                ("zone", "Office".to_string())
+            ],
+            timestamp: "2024-01-14T00:15:00Z".parse().unwrap(),
+            value: 51.2,
+        }));
+
+        assert_eq!(
+            output,
+            r#"# TYPE tado_sensor_humidity_percentage gauge
+tado_sensor_humidity_percentage{type="HEATING",zone="Office"} 52.5 1705190400
+tado_sensor_humidity_percentage{type="HEATING",zone="Office"} 51.2 1705191300
+# TYPE tado_sensor_temperature_value gauge
+tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Office"} 20.1 1705190400
+tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Office"} 19.5 1705191300
+tado_sensor_temperature_value{type="HEATING",unit="fahrenheit",zone="Office"} 68.18 1705190400
+tado_sensor_temperature_value{type="HEATING",unit="fahrenheit",zone="Office"} 67.1 1705191300
+# TYPE tado_setting_temperature_value gauge
+tado_setting_temperature_value{type="HEATING",unit="celsius",zone="Office"} 20 1705189500
+tado_setting_temperature_value{type="HEATING",unit="celsius",zone="Office"} 20 1705190400
+tado_setting_temperature_value{type="HEATING",unit="fahrenheit",zone="Office"} 68 1705189500
+tado_setting_temperature_value{type="HEATING",unit="fahrenheit",zone="Office"} 68 1705190400
+# EOF
+"#
+        );
+    }
+}
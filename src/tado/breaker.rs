@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open { until: Instant },
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open { .. } => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// Stop scraping a tado° API that keeps failing.
+///
+/// After `threshold` consecutive failed scrapes the breaker opens and scrapes
+/// are skipped for `cooldown`. The first scrape after the cooldown is a
+/// half-open probe: full scraping resumes if it succeeds, otherwise the
+/// breaker opens again for another cooldown.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    state: BreakerState,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            threshold,
+            cooldown,
+            consecutive_failures: 0,
+            state: BreakerState::Closed,
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+
+    /// Whether a scrape may run at `now`, moving an open breaker whose
+    /// cooldown elapsed to half-open.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        match self.state {
+            BreakerState::Closed | BreakerState::HalfOpen => true,
+            BreakerState::Open { until } if now >= until => {
+                self.state = BreakerState::HalfOpen;
+                true
+            }
+            BreakerState::Open { .. } => false,
+        }
+    }
+
+    pub fn record(&mut self, success: bool, now: Instant) {
+        if success {
+            self.consecutive_failures = 0;
+            self.state = BreakerState::Closed;
+            return;
+        }
+
+        self.consecutive_failures += 1;
+
+        if self.state == BreakerState::HalfOpen || self.consecutive_failures >= self.threshold {
+            self.state = BreakerState::Open {
+                until: now + self.cooldown,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker() {
+        /*
+        GIVEN a circuit breaker opening after 2 failures for 60 seconds
+        WHEN scrapes fail, the cooldown elapses and probes are recorded
+        THEN scrapes are skipped while open and resume after a successful probe
+        */
+
+        // GIVEN
+        let start = Instant::now();
+        let cooldown = Duration::from_secs(60);
+        let mut breaker = CircuitBreaker::new(2, cooldown);
+
+        // WHEN a single scrape fails
+        assert!(breaker.allow(start));
+        breaker.record(false, start);
+
+        // THEN the breaker stays closed
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        // WHEN a second scrape fails
+        assert!(breaker.allow(start));
+        breaker.record(false, start);
+
+        // THEN the breaker opens until the cooldown elapsed
+        assert_eq!(breaker.state().name(), "open");
+        assert!(!breaker.allow(start + Duration::from_secs(30)));
+
+        // WHEN the probe sent after the cooldown fails
+        assert!(breaker.allow(start + cooldown));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        breaker.record(false, start + cooldown);
+
+        // THEN the breaker opens again for another cooldown
+        assert!(!breaker.allow(start + cooldown + Duration::from_secs(30)));
+
+        // WHEN the next probe succeeds
+        assert!(breaker.allow(start + cooldown * 2));
+        breaker.record(true, start + cooldown * 2);
+
+        // THEN the breaker closes and failures are counted from scratch
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.record(false, start + cooldown * 2);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use log::warn;
+use serde_derive::{Deserialize, Serialize};
+
+/// What the exporter remembers across restarts, to save discovery requests
+/// on cold starts. Tokens and credentials are never written to the cache.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Cache {
+    pub home_id: Option<i32>,
+}
+
+/// The cache, stored as JSON in a file.
+pub struct CacheFile {
+    path: PathBuf,
+}
+
+impl CacheFile {
+    pub fn new(path: PathBuf) -> CacheFile {
+        CacheFile { path }
+    }
+
+    /// Read the cache, empty when the file doesn't exist yet or can't be read.
+    pub fn load(&self) -> Cache {
+        let content = match fs::read(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Cache::default(),
+            Err(e) => {
+                warn!("unable to read cache file {}: {}", self.path.display(), e);
+                return Cache::default();
+            }
+        };
+
+        serde_json::from_slice(&content).unwrap_or_else(|e| {
+            warn!("ignoring invalid cache file {}: {}", self.path.display(), e);
+            Cache::default()
+        })
+    }
+
+    /// Write the cache, which is only an optimization: failures are logged.
+    pub fn store(&self, cache: &Cache) {
+        if let Err(e) = fs::write(&self.path, serde_json::to_vec(cache).unwrap()) {
+            warn!("unable to write cache file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_file() {
+        /*
+        GIVEN a cache file that doesn't exist yet
+        WHEN loading it, then storing a home identifier and loading it again
+        THEN the cache is empty at first, then holds the home identifier
+        */
+
+        // GIVEN
+        let path = std::env::temp_dir().join(format!("tado-cache-{}.json", std::process::id()));
+        let cache_file = CacheFile::new(path.clone());
+
+        // WHEN
+        let empty = cache_file.load();
+        cache_file.store(&Cache {
+            home_id: Some(1234),
+        });
+        let stored = cache_file.load();
+        fs::remove_file(&path).unwrap();
+
+        // THEN
+        assert_eq!(empty, Cache::default());
+        assert_eq!(stored.home_id, Some(1234));
+    }
+}
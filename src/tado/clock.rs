@@ -0,0 +1,68 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+
+#[cfg(test)]
+use std::sync::Mutex;
+#[cfg(test)]
+use std::time::Duration;
+
+/// The source of the current time for time-dependent logic, such as token
+/// renewals, so tests can control it rather than sleep.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// The wall-clock time, to compare with the timestamps of the tado° API.
+    fn utc_now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock standing still until explicitly advanced.
+#[cfg(test)]
+pub struct FakeClock {
+    now: Mutex<(Instant, DateTime<Utc>)>,
+}
+
+#[cfg(test)]
+impl Default for FakeClock {
+    fn default() -> FakeClock {
+        FakeClock::at(Utc::now())
+    }
+}
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn at(utc_now: DateTime<Utc>) -> FakeClock {
+        FakeClock {
+            now: Mutex::new((Instant::now(), utc_now)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        now.0 += duration;
+        now.1 += duration;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.now.lock().unwrap().0
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        self.now.lock().unwrap().1
+    }
+}
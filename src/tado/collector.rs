@@ -0,0 +1,1746 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+
+use super::breaker::CircuitBreaker;
+use super::clock::{Clock, SystemClock};
+use super::debug;
+use super::history;
+use super::metrics;
+use super::model::{
+    DeviceZoneResponse, HomeStateApiResponse, MobileDeviceApiResponse, UnsupportedZoneResponse,
+    WeatherApiResponse, ZoneStateResponse,
+};
+use super::state;
+
+/// A source of zone and weather data that the exporter turns into metrics.
+///
+/// The tado° API client is the production implementation; tests can provide
+/// their own to exercise the metrics output without any HTTP mocking.
+pub trait ZoneDataSource {
+    fn retrieve_zones(&mut self) -> impl Future<Output = Vec<ZoneStateResponse>> + Send;
+    fn retrieve_weather(&mut self) -> impl Future<Output = Option<WeatherApiResponse>> + Send;
+
+    /// The zone served by each device, for sources able to tell.
+    fn retrieve_devices(&mut self) -> impl Future<Output = Vec<DeviceZoneResponse>> + Send {
+        async { Vec::new() }
+    }
+
+    /// The mobile devices of the home's users, for sources able to tell.
+    fn retrieve_mobile_devices(
+        &mut self,
+    ) -> impl Future<Output = Vec<MobileDeviceApiResponse>> + Send {
+        async { Vec::new() }
+    }
+
+    /// The presence of the home, for sources able to tell.
+    fn retrieve_home_state(&mut self) -> impl Future<Output = Option<HomeStateApiResponse>> + Send {
+        async { None }
+    }
+
+    /// The zones the latest `retrieve_zones` skipped, as their state doesn't
+    /// fit the model.
+    fn unsupported_zones(&self) -> Vec<UnsupportedZoneResponse> {
+        Vec::new()
+    }
+
+    /// The zones the latest `retrieve_zones` retrieved before it was
+    /// interrupted, e.g. by the scrape deadline, for sources able to tell.
+    fn collected_zones(&mut self) -> Vec<ZoneStateResponse> {
+        Vec::new()
+    }
+
+    /// The number of requests retried during the latest scrape.
+    fn retries_used(&self) -> u32 {
+        0
+    }
+}
+
+// Keep the counters of a zone missing from the home for a while, so a zone
+// briefly missing from the API responses doesn't reset them.
+const DEFAULT_ZONE_EVICTION_GRACE: Duration = Duration::from_secs(3600);
+
+// Valves report a heating power of a few % while they open or close, so a
+// zone's valves are only considered open from DEFAULT_VALVE_OPEN_THRESHOLD %,
+// and closed again at DEFAULT_VALVE_CLOSE_THRESHOLD %.
+pub const DEFAULT_VALVE_OPEN_THRESHOLD: f64 = 5.0;
+pub const DEFAULT_VALVE_CLOSE_THRESHOLD: f64 = 1.0;
+
+// Rooms don't take longer than this to warm up: estimating they do means the
+// zone isn't really warming up.
+const MAX_TIME_TO_TARGET: Duration = Duration::from_secs(6 * 3600);
+
+pub struct Collector {
+    skip_stale_samples: bool,
+    deadline: Option<Duration>,
+    circuit_breaker: Option<CircuitBreaker>,
+    temperature_bounds: Option<(f64, f64)>,
+    humidity_band: Option<(f64, f64)>,
+    valve_thresholds: (f64, f64),
+    zone_groups: HashMap<String, String>,
+    consecutive_failures: u32,
+    scrape_interval: Option<Duration>,
+    zone_eviction_grace: Duration,
+    hold_on_failure: bool,
+    app_temperatures: bool,
+    clock: Arc<dyn Clock>,
+    sensor_timestamps: HashMap<String, String>,
+    overlay_settings: HashMap<String, Option<Option<f64>>>,
+    open_windows: HashMap<String, bool>,
+    heating_observed_at: HashMap<String, Instant>,
+    // The time and value of the latest inside temperature reading of each
+    // zone.
+    inside_temperatures: HashMap<String, (DateTime<Utc>, f64)>,
+    valves_open: HashMap<String, bool>,
+    // The timestamp and value of the latest outside temperature observation.
+    outside_temperature: Option<(Option<String>, f64)>,
+    // The type of each zone reported, and when it was last seen.
+    zones_seen: HashMap<String, (String, Instant)>,
+}
+
+impl Collector {
+    pub fn new(skip_stale_samples: bool) -> Collector {
+        Collector {
+            skip_stale_samples,
+            deadline: None,
+            circuit_breaker: None,
+            temperature_bounds: None,
+            humidity_band: None,
+            valve_thresholds: (DEFAULT_VALVE_OPEN_THRESHOLD, DEFAULT_VALVE_CLOSE_THRESHOLD),
+            zone_groups: HashMap::new(),
+            consecutive_failures: 0,
+            scrape_interval: None,
+            zone_eviction_grace: DEFAULT_ZONE_EVICTION_GRACE,
+            hold_on_failure: false,
+            app_temperatures: false,
+            clock: Arc::new(SystemClock),
+            sensor_timestamps: HashMap::new(),
+            overlay_settings: HashMap::new(),
+            open_windows: HashMap::new(),
+            heating_observed_at: HashMap::new(),
+            inside_temperatures: HashMap::new(),
+            valves_open: HashMap::new(),
+            outside_temperature: None,
+            zones_seen: HashMap::new(),
+        }
+    }
+
+    /// Bound the duration of a whole scrape, so a slow tado° API can't make
+    /// scrapes pile up. A scrape exceeding the deadline fails: the zones
+    /// retrieved in time are reported, the others are held over or dropped
+    /// like on any failed scrape.
+    pub fn with_deadline(mut self, deadline: Duration) -> Collector {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Skip scrapes while the tado° API keeps failing, see [`CircuitBreaker`].
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Collector {
+        metrics::set_circuit_breaker_state(circuit_breaker.state().name());
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Drop inside temperature readings outside of `min..=max` celsius
+    /// degrees, such as the bogus values reported while a device reconnects.
+    pub fn with_temperature_bounds(mut self, min: f64, max: f64) -> Collector {
+        self.temperature_bounds = Some((min, max));
+        self
+    }
+
+    /// Consider the valves of a zone open once its heating power reaches
+    /// `open` percent, and closed once it drops to `close` percent. In
+    /// between, the valves are left as they were, so they don't flap.
+    pub fn with_valve_thresholds(mut self, open: f64, close: f64) -> Collector {
+        self.valve_thresholds = (open, close);
+        self
+    }
+
+    /// Report whether the humidity of each zone is outside of `min..=max`
+    /// percent, to alert on damp or overly dry rooms.
+    pub fn with_humidity_band(mut self, min: f64, max: f64) -> Collector {
+        self.humidity_band = Some((min, max));
+        self
+    }
+
+    /// Also report the inside temperatures rounded like the tado° app shows
+    /// them, for dashboards to match the app.
+    pub fn with_app_temperatures(mut self, app_temperatures: bool) -> Collector {
+        self.app_temperatures = app_temperatures;
+        self
+    }
+
+    /// Report the group each zone belongs to, e.g. the rooms of an open
+    /// space controlled together. Zones are matched by name.
+    pub fn with_zone_groups(mut self, zone_groups: HashMap<String, String>) -> Collector {
+        self.zone_groups = zone_groups;
+        self
+    }
+
+    /// The interval scrapes are expected at. Heating time is accumulated
+    /// between consecutive scrapes, and never more than this interval at
+    /// once, so missed scrapes don't count as heating time.
+    pub fn with_scrape_interval(mut self, scrape_interval: Duration) -> Collector {
+        self.scrape_interval = Some(scrape_interval);
+        self
+    }
+
+    /// Stop reporting the counters of a zone removed from the home after
+    /// `grace`, rather than as soon as it is missing like its other series.
+    pub fn with_zone_eviction_grace(mut self, grace: Duration) -> Collector {
+        self.zone_eviction_grace = grace;
+        self
+    }
+
+    /// Keep reporting the last values of the zones when a scrape fails,
+    /// flagged as held over, rather than leaving a gap in their series.
+    pub fn with_hold_on_failure(mut self, hold_on_failure: bool) -> Collector {
+        self.hold_on_failure = hold_on_failure;
+        self
+    }
+
+    // Control the time durations are computed from, for reproducible outputs.
+    #[cfg(test)]
+    pub(crate) fn with_clock(mut self, clock: Arc<dyn Clock>) -> Collector {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn collect<S: ZoneDataSource>(&mut self, source: &mut S) {
+        if let Some(circuit_breaker) = &mut self.circuit_breaker {
+            let allowed = circuit_breaker.allow(self.clock.now());
+            metrics::set_circuit_breaker_state(circuit_breaker.state().name());
+
+            if !allowed {
+                warn!("circuit breaker is open, skipping scrape");
+                return;
+            }
+        }
+
+        let started_at = self.clock.now();
+        let deadline_at = self
+            .deadline
+            .map(|deadline| tokio::time::Instant::now() + deadline);
+
+        // the zones retrieved in time are kept when exceeding the deadline,
+        // the rest of the home is only retrieved once they all are
+        let (mut zones, home) = match within(deadline_at, source.retrieve_zones()).await {
+            Some(zones) => {
+                let home = within(deadline_at, async {
+                    (
+                        source.retrieve_weather().await,
+                        source.retrieve_devices().await,
+                        source.retrieve_mobile_devices().await,
+                        source.retrieve_home_state().await,
+                    )
+                })
+                .await;
+                (zones, home)
+            }
+            None => (source.collected_zones(), None),
+        };
+        let completed = home.is_some();
+        let (weather, devices, mobile_devices, home_state) = home.unwrap_or_default();
+
+        if !completed {
+            error!(
+                "scrape did not complete within {:?}, {} zones retrieved in time",
+                self.deadline.unwrap_or_default(),
+                zones.len()
+            );
+            metrics::inc_scrape_deadline_exceeded();
+        }
+
+        // The data source logs and swallows API errors, which leaves no zones
+        // to report: a home always has at least one zone. The zones missing
+        // from a scrape exceeding the deadline are held over or dropped.
+        self.record_scrape(completed && !zones.is_empty());
+        metrics::set_unsupported_zones(&source.unsupported_zones());
+
+        if let Some((min, max)) = self.temperature_bounds {
+            reject_implausible_temperatures(&mut zones, min, max);
+        }
+
+        let stale_zones: Vec<&ZoneStateResponse> = zones
+            .iter()
+            .filter(|zone| self.track_staleness(zone))
+            .collect();
+
+        for zone in zones.iter() {
+            self.track_manual_changes(zone);
+            self.track_open_window_actions(zone);
+            self.track_time_to_target(zone);
+            self.track_valve(zone);
+        }
+
+        let observed_at = self.clock.now();
+        for zone in zones.iter() {
+            self.track_heating(zone, observed_at);
+        }
+
+        // failed scrapes report no zone at all, or only some when exceeding
+        // the deadline, which doesn't mean the others were removed
+        if completed && !zones.is_empty() {
+            self.evict_removed_zones(&zones, observed_at);
+        }
+
+        metrics::set_zones(&zones);
+        if self.app_temperatures {
+            metrics::set_app_inside_temperatures(&zones);
+        }
+        metrics::set_open_window_ages(&zones, self.clock.utc_now());
+        metrics::set_activity_data_ages(&zones, self.clock.utc_now());
+        metrics::set_overlay_desyncs(&zones, self.clock.utc_now());
+        metrics::set_boost_ends(&zones);
+        metrics::set_next_schedule_changes(&zones, self.clock.utc_now());
+        metrics::set_zone_groups(&zones, &self.zone_groups);
+        for zone in zones.iter() {
+            metrics::set_zone_value_stale(&zone.name, false);
+        }
+
+        // Stale sensor samples are only dropped when explicitly requested, as
+        // Prometheus generally prefers continuous series.
+        if self.skip_stale_samples {
+            for zone in stale_zones {
+                metrics::remove_zone_sensor(&zone.name, &zone.state_response.setting.deviceType);
+            }
+        }
+
+        if let Some((min, max)) = self.humidity_band {
+            metrics::set_humidity_out_of_range(&zones, min, max);
+        }
+
+        metrics::set_weather(weather.as_ref());
+        if let Some(weather) = &weather {
+            self.track_outside_temperature(weather);
+        }
+
+        metrics::set_devices(&devices);
+        metrics::set_mobile_devices(&mobile_devices);
+        metrics::set_home_state(home_state.as_ref(), &mobile_devices);
+
+        self.log_summary(source, zones.len(), weather.is_some(), started_at);
+
+        state::set_state(zones, weather);
+        state::read(|state| history::record(state, self.clock.utc_now()));
+    }
+
+    // Sum the scrape up in a single line, to follow the health of the
+    // exporter without the detailed logs of every value.
+    fn log_summary<S: ZoneDataSource>(
+        &self,
+        source: &S,
+        zones: usize,
+        weather: bool,
+        started_at: Instant,
+    ) {
+        info!(
+            "scrape {}: zones={} unsupported_zones={} weather={} retries={} consecutive_failures={} duration={:.3}s",
+            if self.consecutive_failures == 0 {
+                "succeeded"
+            } else {
+                "failed"
+            },
+            zones,
+            source.unsupported_zones().len(),
+            if weather { "ok" } else { "unavailable" },
+            source.retries_used(),
+            self.consecutive_failures,
+            self.clock
+                .now()
+                .saturating_duration_since(started_at)
+                .as_secs_f64()
+        );
+    }
+
+    fn record_scrape(&mut self, success: bool) {
+        debug::set_last_scrape(success);
+
+        self.consecutive_failures = if success {
+            0
+        } else {
+            self.consecutive_failures + 1
+        };
+        metrics::set_consecutive_scrape_failures(self.consecutive_failures);
+
+        if !success {
+            self.hold_or_drop_zones();
+        }
+
+        if let Some(circuit_breaker) = &mut self.circuit_breaker {
+            circuit_breaker.record(success, self.clock.now());
+            metrics::set_circuit_breaker_state(circuit_breaker.state().name());
+        }
+    }
+
+    // A failed scrape leaves the values of the previous one in place: flag
+    // them as held over, or drop them to leave a gap. Counters are kept
+    // either way.
+    fn hold_or_drop_zones(&self) {
+        for (name, (device_type, _)) in self.zones_seen.iter() {
+            if self.hold_on_failure {
+                metrics::set_zone_value_stale(name, true);
+            } else {
+                metrics::remove_zone(name, device_type);
+            }
+        }
+    }
+
+    // Stop reporting the zones missing from the latest scrape, and forget
+    // about them once their grace period is over.
+    fn evict_removed_zones(&mut self, zones: &[ZoneStateResponse], observed_at: Instant) {
+        for zone in zones {
+            self.zones_seen.insert(
+                zone.name.clone(),
+                (zone.state_response.setting.deviceType.clone(), observed_at),
+            );
+        }
+
+        let mut evicted = Vec::new();
+        for (name, (device_type, last_seen_at)) in self.zones_seen.iter() {
+            if zones.iter().any(|zone| &zone.name == name) {
+                continue;
+            }
+
+            metrics::remove_zone(name, device_type);
+
+            if observed_at.saturating_duration_since(*last_seen_at) >= self.zone_eviction_grace {
+                warn!("zone {} was removed from the home", name);
+                metrics::remove_zone_counters(name);
+                evicted.push(name.clone());
+            }
+        }
+
+        for name in evicted {
+            self.zones_seen.remove(&name);
+            self.sensor_timestamps.remove(&name);
+            self.overlay_settings.remove(&name);
+            self.open_windows.remove(&name);
+            self.heating_observed_at.remove(&name);
+            self.inside_temperatures.remove(&name);
+            self.valves_open.remove(&name);
+        }
+    }
+
+    // Count the time since the previous scrape as heating time when the zone
+    // is heating. tado° only reports whether a zone is heating right now, so
+    // this is an approximation that gets better with shorter scrape intervals.
+    fn track_heating(&mut self, zone: &ZoneStateResponse, observed_at: Instant) {
+        let previous = self
+            .heating_observed_at
+            .insert(zone.name.clone(), observed_at);
+
+        let mut heating_time = match previous {
+            Some(previous) if zone.state_response.is_heating() => {
+                observed_at.saturating_duration_since(previous)
+            }
+            _ => Duration::ZERO,
+        };
+        if let Some(scrape_interval) = self.scrape_interval {
+            heating_time = heating_time.min(scrape_interval);
+        }
+
+        metrics::inc_zone_heating_seconds(&zone.name, heating_time.as_secs_f64());
+    }
+
+    // Estimate when a zone reaches its setpoint from the warming rate between
+    // its two latest inside temperature readings. Readings with the same
+    // timestamp are the same reading, which keeps the previous estimate.
+    fn track_time_to_target(&mut self, zone: &ZoneStateResponse) {
+        let reading = zone
+            .state_response
+            .sensorDataPoints
+            .as_ref()
+            .and_then(|sensor_data_points| sensor_data_points.insideTemperature.as_ref())
+            .and_then(|inside_temperature| {
+                let timestamp = inside_temperature.timestamp.as_ref()?;
+                let measured_at = DateTime::parse_from_rfc3339(timestamp).ok()?;
+                Some((measured_at.with_timezone(&Utc), inside_temperature.celsius))
+            });
+        let reading = match reading {
+            Some(reading) => reading,
+            None => {
+                self.inside_temperatures.remove(&zone.name);
+                metrics::set_zone_time_to_target(&zone.name, None);
+                return;
+            }
+        };
+
+        let previous = match self.inside_temperatures.insert(zone.name.clone(), reading) {
+            Some(previous) if previous.0 == reading.0 => return,
+            previous => previous,
+        };
+        let setpoint = zone
+            .state_response
+            .setting
+            .temperature
+            .as_ref()
+            .map(|temperature| temperature.celsius);
+
+        let estimate = match (previous, setpoint) {
+            (Some(previous), Some(setpoint)) => time_to_target(previous, reading, setpoint),
+            _ => None,
+        };
+        metrics::set_zone_time_to_target(&zone.name, estimate);
+    }
+
+    // tado° valves are either open or closed, although they report a heating
+    // power in %: tell them apart with hysteresis, so a heating power
+    // hovering around a single threshold doesn't flap between the two.
+    fn track_valve(&mut self, zone: &ZoneStateResponse) {
+        let state = &zone.state_response;
+        let heating_power = match &state.activityDataPoints.heatingPower {
+            Some(_) if !state.setting.is_powered_on() => 0.0,
+            Some(heating_power) => heating_power.percentage,
+            None => {
+                self.valves_open.remove(&zone.name);
+                metrics::set_zone_valve_open(&zone.name, None);
+                return;
+            }
+        };
+
+        let (open_threshold, close_threshold) = self.valve_thresholds;
+        let open = if heating_power >= open_threshold {
+            true
+        } else if heating_power <= close_threshold {
+            false
+        } else {
+            self.valves_open.get(&zone.name).copied().unwrap_or(false)
+        };
+
+        self.valves_open.insert(zone.name.clone(), open);
+        metrics::set_zone_valve_open(&zone.name, Some(open));
+    }
+
+    // Report how much the outside temperature changed since the previous
+    // observation. tado° updates the weather less often than scrapes happen,
+    // so a reading with the same timestamp as the previous one is the same
+    // observation; readings without a timestamp are all new observations.
+    fn track_outside_temperature(&mut self, weather: &WeatherApiResponse) {
+        let outside_temperature = &weather.outsideTemperature;
+
+        match &self.outside_temperature {
+            Some((timestamp, _))
+                if timestamp.is_some() && *timestamp == outside_temperature.timestamp =>
+            {
+                return
+            }
+            Some((_, previous)) => metrics::set_weather_outside_temperature_delta(
+                outside_temperature.celsius - previous,
+            ),
+            // the first observation has nothing to compare with
+            None => {}
+        }
+
+        self.outside_temperature = Some((
+            outside_temperature.timestamp.clone(),
+            outside_temperature.celsius,
+        ));
+    }
+
+    // A zone is stale when its sensor timestamp did not advance since the
+    // previous scrape.
+    fn track_staleness(&mut self, zone: &ZoneStateResponse) -> bool {
+        let inside_temperature = zone
+            .state_response
+            .sensorDataPoints
+            .as_ref()
+            .and_then(|sensor_data_points| sensor_data_points.insideTemperature.as_ref());
+        let timestamp = match inside_temperature {
+            Some(inside_temperature) => match &inside_temperature.timestamp {
+                Some(timestamp) => timestamp.clone(),
+                None => return false,
+            },
+            None => return false,
+        };
+
+        let stale = self.sensor_timestamps.get(&zone.name) == Some(&timestamp);
+        self.sensor_timestamps.insert(zone.name.clone(), timestamp);
+
+        metrics::set_zone_stale(&zone.name, stale);
+
+        stale
+    }
+
+    // tado° does not expose how many times a zone was manually adjusted, so
+    // manual changes are derived from the overlays observed across scrapes:
+    // an overlay appearing, or its setting changing, counts as one change.
+    // Changes happening between two scrapes can't be told apart, and an
+    // overlay already present on the first scrape isn't counted.
+    fn track_manual_changes(&mut self, zone: &ZoneStateResponse) {
+        let overlay_setting = zone.state_response.overlay.as_ref().map(|overlay| {
+            overlay
+                .setting
+                .temperature
+                .as_ref()
+                .map(|temperature| temperature.celsius)
+        });
+
+        if let Some(previous) = self
+            .overlay_settings
+            .insert(zone.name.clone(), overlay_setting)
+        {
+            if overlay_setting.is_some() && overlay_setting != previous {
+                metrics::inc_zone_manual_changes(&zone.name);
+            }
+        }
+    }
+
+    // Count the open window modes activated and ended between two scrapes.
+    // tado° doesn't report these actions, whether taken by Auto-Assist or
+    // confirmed in the app, so they are derived from the zone states.
+    fn track_open_window_actions(&mut self, zone: &ZoneStateResponse) {
+        let open_window = zone.state_response.openWindow.is_some();
+
+        match self.open_windows.insert(zone.name.clone(), open_window) {
+            Some(false) if open_window => {
+                metrics::inc_home_auto_actions(&zone.name, "open_window_activated")
+            }
+            Some(true) if !open_window => {
+                metrics::inc_home_auto_actions(&zone.name, "open_window_ended")
+            }
+            _ => {}
+        }
+    }
+}
+
+// Drop the inside temperature readings outside of `min..=max` from `zones`,
+// before anything tracks, exports or stores them.
+fn reject_implausible_temperatures(zones: &mut [ZoneStateResponse], min: f64, max: f64) {
+    for zone in zones.iter_mut() {
+        let Some(sensor_data_points) = zone.state_response.sensorDataPoints.as_mut() else {
+            continue;
+        };
+        let implausible = sensor_data_points
+            .insideTemperature
+            .as_ref()
+            .is_some_and(|inside_temperature| !(min..=max).contains(&inside_temperature.celsius));
+
+        if implausible {
+            if let Some(inside_temperature) = sensor_data_points.insideTemperature.take() {
+                warn!(
+                    "-> {} -> rejected implausible temperature: {}",
+                    zone.name, inside_temperature.celsius
+                );
+            }
+            metrics::reject_zone_temperature(&zone.name, &zone.state_response.setting.deviceType);
+        }
+    }
+}
+
+// Run `future` to completion, unless `deadline_at` is reached first.
+async fn within<T>(
+    deadline_at: Option<tokio::time::Instant>,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    match deadline_at {
+        Some(deadline_at) => tokio::time::timeout_at(deadline_at, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+// Project linearly when the latest reading reaches `setpoint`, if the zone is
+// warming towards it. Projections further out than MAX_TIME_TO_TARGET come
+// from rates too small to tell apart from sensor noise, and are dropped.
+fn time_to_target(
+    previous: (DateTime<Utc>, f64),
+    latest: (DateTime<Utc>, f64),
+    setpoint: f64,
+) -> Option<f64> {
+    let elapsed = (latest.0 - previous.0).num_milliseconds() as f64 / 1000.0;
+    let warming = latest.1 - previous.1;
+    if elapsed <= 0.0 || warming <= 0.0 || latest.1 >= setpoint {
+        return None;
+    }
+
+    let seconds = (setpoint - latest.1) * elapsed / warming;
+    (seconds <= MAX_TIME_TO_TARGET.as_secs_f64()).then_some(seconds)
+}
+
+// Each test runs on its own runtime, so holding the registry lock across await
+// points cannot deadlock.
+#[cfg(test)]
+#[allow(clippy::await_holding_lock)]
+mod tests {
+    use super::*;
+
+    use crate::tado::clock::FakeClock;
+    use crate::tado::logs;
+    use crate::tado::metrics::{lock_registry, renderer};
+
+    use hyper::{body, Body, Request};
+    use rstest::*;
+
+    struct MockDataSource {
+        zones: Vec<(&'static str, String)>,
+        weather: Option<&'static str>,
+    }
+
+    impl ZoneDataSource for MockDataSource {
+        async fn retrieve_zones(&mut self) -> Vec<ZoneStateResponse> {
+            self.zones
+                .iter()
+                .map(|(name, state)| ZoneStateResponse {
+                    name: name.to_string(),
+                    state_response: serde_json::from_str(state).unwrap(),
+                    scheduled_setting: None,
+                    away_setting: None,
+                })
+                .collect()
+        }
+
+        async fn retrieve_weather(&mut self) -> Option<WeatherApiResponse> {
+            self.weather
+                .map(|weather| serde_json::from_str(weather).unwrap())
+        }
+    }
+
+    struct SlowDataSource {
+        source: MockDataSource,
+        slow_zones: Vec<&'static str>,
+        delay: Duration,
+        collected: Vec<ZoneStateResponse>,
+    }
+
+    impl ZoneDataSource for SlowDataSource {
+        async fn retrieve_zones(&mut self) -> Vec<ZoneStateResponse> {
+            self.collected.clear();
+            for zone in self.source.retrieve_zones().await {
+                if self.slow_zones.contains(&zone.name.as_str()) {
+                    tokio::time::sleep(self.delay).await;
+                }
+                self.collected.push(zone);
+            }
+
+            std::mem::take(&mut self.collected)
+        }
+
+        async fn retrieve_weather(&mut self) -> Option<WeatherApiResponse> {
+            self.source.retrieve_weather().await
+        }
+
+        fn collected_zones(&mut self) -> Vec<ZoneStateResponse> {
+            std::mem::take(&mut self.collected)
+        }
+    }
+
+    struct RetriedDataSource {
+        source: MockDataSource,
+        retries: u32,
+    }
+
+    impl ZoneDataSource for RetriedDataSource {
+        async fn retrieve_zones(&mut self) -> Vec<ZoneStateResponse> {
+            self.source.retrieve_zones().await
+        }
+
+        async fn retrieve_weather(&mut self) -> Option<WeatherApiResponse> {
+            self.source.retrieve_weather().await
+        }
+
+        fn retries_used(&self) -> u32 {
+            self.retries
+        }
+    }
+
+    fn zone_state(setting: f64, inside: f64, timestamp: &str) -> String {
+        format!(
+            r#"{{
+                "setting": {{
+                    "type": "HEATING",
+                    "temperature": {{ "celsius": {}, "fahrenheit": {} }}
+                }},
+                "activityDataPoints": {{
+                    "heatingPower": {{ "percentage": 42.0 }}
+                }},
+                "sensorDataPoints": {{
+                    "insideTemperature": {{
+                        "celsius": {},
+                        "fahrenheit": {},
+                        "timestamp": "{}"
+                    }},
+                    "humidity": {{ "percentage": 55.5 }}
+                }}
+            }}"#,
+            setting,
+            setting * 9.0 / 5.0 + 32.0,
+            inside,
+            inside * 9.0 / 5.0 + 32.0,
+            timestamp
+        )
+    }
+
+    fn overlay_zone_state(overlay_setting: Option<f64>) -> String {
+        let overlay = match overlay_setting {
+            Some(celsius) => format!(
+                r#"{{
+                    "type": "MANUAL",
+                    "setting": {{
+                        "type": "HEATING",
+                        "temperature": {{ "celsius": {}, "fahrenheit": {} }}
+                    }}
+                }}"#,
+                celsius,
+                celsius * 9.0 / 5.0 + 32.0
+            ),
+            None => "null".to_string(),
+        };
+
+        format!(
+            r#"{{
+                "setting": {{ "type": "HEATING", "temperature": null }},
+                "activityDataPoints": {{}},
+                "sensorDataPoints": {{}},
+                "overlay": {}
+            }}"#,
+            overlay
+        )
+    }
+
+    async fn render() -> String {
+        let response = renderer(Request::new(Body::empty())).await.unwrap();
+        let body = body::to_bytes(response.into_body()).await.unwrap();
+
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    const GOLDEN_OUTPUT: &str = r#"# HELP tado_activity_heating_power_percentage The % of heating power in a specific zone.
+# TYPE tado_activity_heating_power_percentage gauge
+tado_activity_heating_power_percentage{type="HEATING",zone="Bathroom"} 42
+tado_activity_heating_power_percentage{type="HEATING",zone="Living room"} 42
+# HELP tado_consecutive_scrape_failures The number of scrapes failed in a row, 0 after a successful scrape.
+# TYPE tado_consecutive_scrape_failures gauge
+tado_consecutive_scrape_failures 0
+# HELP tado_sensor_humidity_percentage The % of humidity in a specific zone.
+# TYPE tado_sensor_humidity_percentage gauge
+tado_sensor_humidity_percentage{type="HEATING",zone="Bathroom"} 55.5
+tado_sensor_humidity_percentage{type="HEATING",zone="Living room"} 55.5
+# HELP tado_sensor_temperature_value The temperature of a specific zone in celsius degres.
+# TYPE tado_sensor_temperature_value gauge
+tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Bathroom"} 22.5
+tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Living room"} 20
+tado_sensor_temperature_value{type="HEATING",unit="fahrenheit",zone="Bathroom"} 72.5
+tado_sensor_temperature_value{type="HEATING",unit="fahrenheit",zone="Living room"} 68
+# HELP tado_sensor_window_opened 1 if the sensor detected a window is open, 0 otherwise.
+# TYPE tado_sensor_window_opened gauge
+tado_sensor_window_opened{type="HEATING",zone="Bathroom"} 0
+tado_sensor_window_opened{type="HEATING",zone="Living room"} 0
+# HELP tado_setting_temperature_value The temperature of a specific zone in celsius degres.
+# TYPE tado_setting_temperature_value gauge
+tado_setting_temperature_value{type="HEATING",unit="celsius",zone="Bathroom"} 24
+tado_setting_temperature_value{type="HEATING",unit="celsius",zone="Living room"} 21
+tado_setting_temperature_value{type="HEATING",unit="fahrenheit",zone="Bathroom"} 75.2
+tado_setting_temperature_value{type="HEATING",unit="fahrenheit",zone="Living room"} 69.8
+# HELP tado_weather_solar_intensity_ratio Solar intensity outside the house, scaled from 0 to 1.
+# TYPE tado_weather_solar_intensity_ratio gauge
+tado_weather_solar_intensity_ratio 0.183
+# HELP tado_weather_state 1 for the current weather state outside the house.
+# TYPE tado_weather_state gauge
+tado_weather_state{type="WEATHER_STATE",value="SUN"} 1
+# HELP tado_zone_heating_seconds_total The number of seconds a specific zone was observed heating.
+# TYPE tado_zone_heating_seconds_total counter
+tado_zone_heating_seconds_total{zone="Bathroom"} 0
+tado_zone_heating_seconds_total{zone="Living room"} 0
+# HELP tado_zone_overlay_desync 1 if the overlay of a specific zone is still reported although it expired, 0 otherwise.
+# TYPE tado_zone_overlay_desync gauge
+tado_zone_overlay_desync{zone="Bathroom"} 0
+tado_zone_overlay_desync{zone="Living room"} 0
+# HELP tado_zone_power_on 1 if a specific zone is switched on, 0 if it is off.
+# TYPE tado_zone_power_on gauge
+tado_zone_power_on{zone="Bathroom"} 1
+tado_zone_power_on{zone="Living room"} 1
+# HELP tado_zone_stale 1 if the zone's sensor readings did not change since the previous scrape, 0 otherwise.
+# TYPE tado_zone_stale gauge
+tado_zone_stale{zone="Bathroom"} 0
+tado_zone_stale{zone="Living room"} 0
+# HELP tado_zone_value_stale 1 if the values of a specific zone are held over from a previous scrape, 0 otherwise.
+# TYPE tado_zone_value_stale gauge
+tado_zone_value_stale{zone="Bathroom"} 0
+tado_zone_value_stale{zone="Living room"} 0
+# HELP tado_zone_valve_open 1 if the valves of a specific zone are open, as derived from its heating power, 0 otherwise.
+# TYPE tado_zone_valve_open gauge
+tado_zone_valve_open{zone="Bathroom"} 1
+tado_zone_valve_open{zone="Living room"} 1
+# HELP weather_outside_temperature Temperature outside the house.
+# TYPE weather_outside_temperature gauge
+weather_outside_temperature{unit="celsius"} 10
+weather_outside_temperature{unit="fahrenheit"} 50
+# HELP weather_solar_intensity Solar intensity outside the house.
+# TYPE weather_solar_intensity gauge
+weather_solar_intensity 18.3
+"#;
+
+    #[actix_rt::test]
+    async fn test_collect_golden_output() {
+        /*
+        GIVEN a mock data source with two zones and weather data
+        WHEN collecting from it and rendering the metrics
+        THEN the rendered output matches the golden output
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![
+                (
+                    "Living room",
+                    zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z"),
+                ),
+                (
+                    "Bathroom",
+                    zone_state(24.0, 22.5, "2022-09-03T17:43:41.088Z"),
+                ),
+            ],
+            weather: Some(
+                r#"{
+                    "solarIntensity": { "type": "PERCENTAGE", "percentage": 18.3 },
+                    "outsideTemperature": {
+                        "type": "TEMPERATURE",
+                        "celsius": 10.0,
+                        "fahrenheit": 50.0
+                    },
+                    "weatherState": { "type": "WEATHER_STATE", "value": "SUN" }
+                }"#,
+            ),
+        };
+
+        // WHEN
+        Collector::new(false).collect(&mut source).await;
+
+        // THEN
+        assert_eq!(render().await, GOLDEN_OUTPUT);
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_zone_groups() {
+        /*
+        GIVEN two zones configured in the same group, and an ungrouped zone
+        WHEN collecting from them
+        THEN the group of the grouped zones is reported
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![
+                (
+                    "Kitchen",
+                    zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z"),
+                ),
+                (
+                    "Living room",
+                    zone_state(21.0, 20.5, "2022-09-03T17:43:41.088Z"),
+                ),
+                ("Office", zone_state(19.0, 19.0, "2022-09-03T17:43:41.088Z")),
+            ],
+            weather: None,
+        };
+        let zone_groups = HashMap::from([
+            ("Kitchen".to_string(), "Open space".to_string()),
+            ("Living room".to_string(), "Open space".to_string()),
+        ]);
+
+        // WHEN
+        Collector::new(false)
+            .with_zone_groups(zone_groups)
+            .collect(&mut source)
+            .await;
+
+        // THEN
+        let output = render().await;
+
+        assert!(output.contains(r#"tado_zone_group_info{group="Open space",zone="Kitchen"} 1"#));
+        assert!(output.contains(r#"tado_zone_group_info{group="Open space",zone="Living room"} 1"#));
+        assert!(!output
+            .lines()
+            .any(|line| line.starts_with("tado_zone_group_info") && line.contains("Office")));
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_zone_without_sensors() {
+        /*
+        GIVEN a hot water zone without any sensor data points, and a heating zone
+        WHEN collecting from them
+        THEN the hot water zone has no sensor metrics and the heating zone is
+             still reported
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![
+                (
+                    "Hot water",
+                    r#"{
+                        "setting": { "type": "HOT_WATER", "power": "ON", "temperature": null },
+                        "activityDataPoints": {}
+                    }"#
+                    .to_string(),
+                ),
+                ("Office", zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z")),
+            ],
+            weather: None,
+        };
+
+        // WHEN
+        Collector::new(false)
+            .with_temperature_bounds(-50.0, 80.0)
+            .collect(&mut source)
+            .await;
+
+        // THEN
+        let output = render().await;
+
+        assert!(!output.contains(r#"tado_sensor_temperature_value{type="HOT_WATER""#));
+        assert!(!output.contains(r#"tado_sensor_humidity_percentage{type="HOT_WATER""#));
+        assert!(
+            output.contains(r#"tado_sensor_window_opened{type="HOT_WATER",zone="Hot water"} 0"#)
+        );
+        assert!(output.contains(
+            r#"tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Office"} 20"#
+        ));
+        assert!(output
+            .contains(r#"tado_sensor_humidity_percentage{type="HEATING",zone="Office"} 55.5"#));
+    }
+
+    #[rstest(skip_stale_samples, case(false), case(true))]
+    #[actix_rt::test]
+    async fn test_collect_stale_zone(skip_stale_samples: bool) {
+        /*
+        GIVEN a zone whose sensor timestamp does not advance between scrapes
+        WHEN collecting twice
+        THEN the zone is flagged as stale, and its sensor samples are only
+             dropped when skipping stale samples
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![
+                ("Office", zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z")),
+                (
+                    "Kitchen",
+                    zone_state(21.0, 19.0, "2022-09-03T17:43:41.088Z"),
+                ),
+            ],
+            weather: None,
+        };
+        let mut collector = Collector::new(skip_stale_samples);
+
+        collector.collect(&mut source).await;
+        let output = render().await;
+
+        assert!(output.contains(r#"tado_zone_stale{zone="Office"} 0"#));
+        assert!(output.contains(r#"tado_zone_stale{zone="Kitchen"} 0"#));
+
+        // WHEN
+        source.zones[1].1 = zone_state(21.0, 19.5, "2022-09-03T17:48:41.088Z");
+        collector.collect(&mut source).await;
+        let output = render().await;
+
+        // THEN
+        assert!(output.contains(r#"tado_zone_stale{zone="Office"} 1"#));
+        assert!(output.contains(r#"tado_zone_stale{zone="Kitchen"} 0"#));
+        assert!(output.contains(
+            r#"tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Kitchen"} 19.5"#
+        ));
+        assert!(output
+            .contains(r#"tado_sensor_humidity_percentage{type="HEATING",zone="Kitchen"} 55.5"#));
+        assert_eq!(
+            output.contains(
+                r#"tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Office"} 20"#
+            ),
+            !skip_stale_samples
+        );
+        assert_eq!(
+            output.contains(r#"tado_sensor_humidity_percentage{type="HEATING",zone="Office"}"#),
+            !skip_stale_samples
+        );
+    }
+
+    fn open_window_zone_state(open_window: bool) -> String {
+        let open_window = if open_window {
+            r#"{ "detectedTime": "2022-09-03T17:40:12Z", "durationInSeconds": 900 }"#
+        } else {
+            "null"
+        };
+
+        format!(
+            r#"{{
+                "setting": {{ "type": "HEATING", "temperature": null }},
+                "openWindow": {},
+                "activityDataPoints": {{}},
+                "sensorDataPoints": {{}}
+            }}"#,
+            open_window
+        )
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_open_window_actions() {
+        /*
+        GIVEN a zone whose open window mode is activated and ended twice
+              across scrapes, starting with an open window
+        WHEN collecting after each change
+        THEN every activation and end observed after the first scrape is
+             counted
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![("Office", open_window_zone_state(true))],
+            weather: None,
+        };
+        let mut collector = Collector::new(false);
+
+        // WHEN
+        for open_window in [true, true, false, true, false, false] {
+            source.zones[0].1 = open_window_zone_state(open_window);
+            collector.collect(&mut source).await;
+        }
+
+        // THEN
+        let output = render().await;
+
+        assert!(output.contains(r#"tado_home_auto_actions_total{type="open_window_activated"} 1"#));
+        assert!(output.contains(r#"tado_home_auto_actions_total{type="open_window_ended"} 2"#));
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_manual_changes() {
+        /*
+        GIVEN a zone whose overlay changes across scrapes
+        WHEN collecting after each change
+        THEN every new overlay or overlay setting change is counted
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![("Office", overlay_zone_state(Some(22.0)))],
+            weather: None,
+        };
+        let mut collector = Collector::new(false);
+
+        // WHEN
+        for overlay_setting in [Some(22.0), Some(22.0), Some(23.0), None, Some(22.0)] {
+            source.zones[0].1 = overlay_zone_state(overlay_setting);
+            collector.collect(&mut source).await;
+        }
+
+        // THEN
+        assert!(render()
+            .await
+            .contains(r#"tado_zone_manual_changes_total{zone="Office"} 2"#));
+    }
+
+    #[rstest(inside, expected_rejected,
+        case(-50.0, false),
+        case(-50.1, true),
+        case(80.0, false),
+        case(80.1, true)
+    )]
+    #[actix_rt::test]
+    async fn test_collect_temperature_bounds(inside: f64, expected_rejected: bool) {
+        /*
+        GIVEN a zone reporting an inside temperature around the bounds
+        WHEN collecting with temperature bounds
+        THEN readings outside of the bounds are dropped and counted
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![
+                (
+                    "Office",
+                    zone_state(21.0, inside, "2022-09-03T17:43:41.088Z"),
+                ),
+                (
+                    "Kitchen",
+                    zone_state(21.0, 19.0, "2022-09-03T17:43:41.088Z"),
+                ),
+            ],
+            weather: None,
+        };
+        let mut collector = Collector::new(false).with_temperature_bounds(-50.0, 80.0);
+
+        // WHEN
+        collector.collect(&mut source).await;
+
+        // THEN
+        let output = render().await;
+
+        assert_eq!(
+            output.contains(&format!(
+                r#"tado_sensor_temperature_value{{type="HEATING",unit="celsius",zone="Office"}} {}"#,
+                inside
+            )),
+            !expected_rejected
+        );
+        assert_eq!(
+            output.contains(r#"tado_zone_rejected_readings_total{zone="Office"} 1"#),
+            expected_rejected
+        );
+        assert!(output.contains(
+            r#"tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Kitchen"} 19"#
+        ));
+        assert!(!output.contains(r#"tado_zone_rejected_readings_total{zone="Kitchen"}"#));
+        assert!(output
+            .contains(r#"tado_sensor_humidity_percentage{type="HEATING",zone="Office"} 55.5"#));
+    }
+
+    #[rstest(hold_on_failure, case(true), case(false))]
+    #[actix_rt::test]
+    async fn test_collect_deadline(hold_on_failure: bool) {
+        /*
+        GIVEN a home whose zones were all collected, the last one becoming
+              slow to retrieve
+        WHEN collecting again with a deadline the slow zone exceeds
+        THEN the scrape fails, the zones retrieved in time are reported, and
+             only the slow zone is held over or dropped
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = SlowDataSource {
+            source: MockDataSource {
+                zones: vec![
+                    ("Office", zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z")),
+                    (
+                        "Bedroom",
+                        zone_state(18.0, 17.0, "2022-09-03T17:43:41.088Z"),
+                    ),
+                    (
+                        "Kitchen",
+                        zone_state(21.0, 19.0, "2022-09-03T17:43:41.088Z"),
+                    ),
+                ],
+                weather: None,
+            },
+            slow_zones: vec!["Kitchen"],
+            delay: Duration::ZERO,
+            collected: Vec::new(),
+        };
+        let mut collector = Collector::new(false)
+            .with_deadline(Duration::from_millis(100))
+            .with_hold_on_failure(hold_on_failure);
+        collector.collect(&mut source).await;
+
+        // WHEN
+        source.source.zones = vec![
+            ("Office", zone_state(21.0, 20.5, "2022-09-03T17:48:41.088Z")),
+            (
+                "Bedroom",
+                zone_state(18.0, 17.5, "2022-09-03T17:48:41.088Z"),
+            ),
+            (
+                "Kitchen",
+                zone_state(21.0, 19.5, "2022-09-03T17:48:41.088Z"),
+            ),
+        ];
+        source.delay = Duration::from_millis(1000);
+        collector.collect(&mut source).await;
+
+        // THEN
+        let output = render().await;
+        let has_line = |line: &str| output.lines().any(|l| l == line);
+
+        for (zone, temperature) in [("Office", "20.5"), ("Bedroom", "17.5")] {
+            assert!(has_line(&format!(
+                r#"tado_sensor_temperature_value{{type="HEATING",unit="celsius",zone="{}"}} {}"#,
+                zone, temperature
+            )));
+            assert!(has_line(&format!(
+                r#"tado_zone_value_stale{{zone="{}"}} 0"#,
+                zone
+            )));
+        }
+        assert_eq!(
+            has_line(
+                r#"tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Kitchen"} 19"#
+            ),
+            hold_on_failure
+        );
+        assert_eq!(
+            has_line(r#"tado_zone_value_stale{zone="Kitchen"} 1"#),
+            hold_on_failure
+        );
+        assert!(has_line("tado_scrape_deadline_exceeded_total 1"));
+        assert!(has_line("tado_consecutive_scrape_failures 1"));
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_circuit_breaker() {
+        /*
+        GIVEN a data source failing to retrieve any zone
+        WHEN collecting with a circuit breaker opening after 2 failures
+        THEN the breaker opens and the following scrapes are skipped
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![],
+            weather: None,
+        };
+        let mut collector = Collector::new(false)
+            .with_circuit_breaker(CircuitBreaker::new(2, Duration::from_secs(3600)));
+
+        // WHEN
+        for _ in 0..2 {
+            collector.collect(&mut source).await;
+        }
+
+        source.zones = vec![("Office", zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z"))];
+        collector.collect(&mut source).await;
+
+        // THEN
+        let output = render().await;
+
+        assert!(output.contains(r#"tado_circuit_breaker_state{state="open"} 1"#));
+        assert!(output.contains(r#"tado_circuit_breaker_state{state="closed"} 0"#));
+        assert!(output.contains(r#"tado_circuit_breaker_state{state="half_open"} 0"#));
+        assert!(!output.contains("tado_sensor_temperature_value"));
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_consecutive_failures() {
+        /*
+        GIVEN a data source failing to retrieve any zone
+        WHEN collecting a few times, then once the zones are back
+        THEN the failures are counted until the successful scrape resets them
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![],
+            weather: None,
+        };
+        let mut collector = Collector::new(false);
+
+        // WHEN
+        for _ in 0..3 {
+            collector.collect(&mut source).await;
+        }
+        let failing = render().await;
+
+        source.zones = vec![("Office", zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z"))];
+        collector.collect(&mut source).await;
+        let recovered = render().await;
+
+        // THEN
+        assert!(failing.contains("tado_consecutive_scrape_failures 3"));
+        assert!(recovered.contains("tado_consecutive_scrape_failures 0"));
+    }
+
+    #[rstest(hold_on_failure, case(true), case(false))]
+    #[actix_rt::test]
+    async fn test_collect_hold_on_failure(hold_on_failure: bool) {
+        /*
+        GIVEN a zone scraped successfully, then a failing scrape
+        WHEN collecting, holding the values on failure or not
+        THEN the values are either reported again and flagged as held, or
+             dropped, the counters being kept either way
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![("Office", zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z"))],
+            weather: None,
+        };
+        let mut collector = Collector::new(false).with_hold_on_failure(hold_on_failure);
+
+        // WHEN
+        collector.collect(&mut source).await;
+        let succeeded = render().await;
+
+        source.zones = vec![];
+        collector.collect(&mut source).await;
+        let failed = render().await;
+
+        // THEN
+        assert!(succeeded.contains(r#"tado_zone_value_stale{zone="Office"} 0"#));
+
+        let temperature =
+            r#"tado_sensor_temperature_value{type="HEATING",unit="celsius",zone="Office"} 20"#;
+        assert_eq!(failed.contains(temperature), hold_on_failure);
+        assert_eq!(
+            failed.contains(r#"tado_zone_value_stale{zone="Office"} 1"#),
+            hold_on_failure
+        );
+        assert!(failed.contains(r#"tado_zone_heating_seconds_total{zone="Office"} 0"#));
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_heating_seconds() {
+        /*
+        GIVEN a zone heating, then idle, scraped every minute with a few
+              scrapes missed
+        WHEN collecting on every scrape
+        THEN the heating time is accumulated, without counting missed scrapes
+             nor the time spent idle
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let clock = Arc::new(FakeClock::default());
+        let mut source = MockDataSource {
+            zones: vec![("Office", zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z"))],
+            weather: None,
+        };
+        let mut collector = Collector::new(false)
+            .with_scrape_interval(Duration::from_secs(60))
+            .with_clock(clock.clone());
+
+        // WHEN
+        collector.collect(&mut source).await;
+        for elapsed_seconds in [60, 30, 600] {
+            clock.advance(Duration::from_secs(elapsed_seconds));
+            collector.collect(&mut source).await;
+        }
+
+        source.zones = vec![(
+            "Office",
+            r#"{
+                "setting": { "type": "HEATING", "power": "ON", "temperature": null },
+                "activityDataPoints": { "heatingPower": { "percentage": 0.0 } }
+            }"#
+            .to_string(),
+        )];
+        clock.advance(Duration::from_secs(60));
+        collector.collect(&mut source).await;
+
+        // THEN
+        let output = render().await;
+
+        assert!(output.contains(r#"tado_zone_heating_seconds_total{zone="Office"} 150"#));
+    }
+
+    fn weather(celsius: f64, timestamp: &str) -> &'static str {
+        Box::leak(
+            format!(
+                r#"{{
+                    "solarIntensity": {{ "type": "PERCENTAGE", "percentage": 18.3 }},
+                    "outsideTemperature": {{
+                        "type": "TEMPERATURE",
+                        "celsius": {},
+                        "fahrenheit": {},
+                        "timestamp": "{}"
+                    }}
+                }}"#,
+                celsius,
+                celsius * 9.0 / 5.0 + 32.0,
+                timestamp
+            )
+            .into_boxed_str(),
+        )
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_outside_temperature_delta() {
+        /*
+        GIVEN a home whose outside temperature is observed three times, the
+              second observation being reported twice
+        WHEN collecting after each observation
+        THEN the delta is omitted after the first observation, then reports
+             the change since the previous observation
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![("Office", zone_state(22.0, 20.0, "2022-09-03T17:43:41.088Z"))],
+            weather: Some(weather(10.0, "2022-09-03T17:30:00.000Z")),
+        };
+        let mut collector = Collector::new(false);
+
+        // WHEN
+        collector.collect(&mut source).await;
+        let first = render().await;
+
+        source.weather = Some(weather(11.5, "2022-09-03T17:45:00.000Z"));
+        collector.collect(&mut source).await;
+        collector.collect(&mut source).await;
+        let second = render().await;
+
+        source.weather = Some(weather(10.75, "2022-09-03T18:00:00.000Z"));
+        collector.collect(&mut source).await;
+        let third = render().await;
+
+        // THEN
+        assert!(!first.contains("tado_weather_outside_temperature_delta_celsius "));
+        assert!(second.contains("tado_weather_outside_temperature_delta_celsius 1.5"));
+        assert!(third.contains("tado_weather_outside_temperature_delta_celsius -0.75"));
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_time_to_target() {
+        /*
+        GIVEN a zone set to 21°C warming from 19°C to 19.5°C in 10 minutes,
+              then cooling down
+        WHEN collecting after each reading
+        THEN the time to target is omitted after the first reading, projects
+             the warming rate after the second one, and is omitted again once
+             the zone cools down
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![("Office", zone_state(21.0, 19.0, "2022-09-03T17:40:00.000Z"))],
+            weather: None,
+        };
+        let mut collector = Collector::new(false);
+
+        // WHEN
+        collector.collect(&mut source).await;
+        let first = render().await;
+
+        source.zones = vec![("Office", zone_state(21.0, 19.5, "2022-09-03T17:50:00.000Z"))];
+        collector.collect(&mut source).await;
+        let warming = render().await;
+
+        source.zones = vec![(
+            "Office",
+            zone_state(21.0, 19.25, "2022-09-03T18:00:00.000Z"),
+        )];
+        collector.collect(&mut source).await;
+        let cooling = render().await;
+
+        // THEN
+        assert!(!first.contains("tado_zone_time_to_target_seconds{"));
+        assert!(warming
+            .lines()
+            .any(|l| l == r#"tado_zone_time_to_target_seconds{zone="Office"} 1800"#));
+        assert!(!cooling.contains("tado_zone_time_to_target_seconds{"));
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_rejected_temperature() {
+        /*
+        GIVEN a zone set to 21°C reading 19°C, then an implausible -60°C,
+              then 19.5°C
+        WHEN collecting with temperature bounds after each reading
+        THEN the implausible reading is left out of the state, and the time
+             to target is never projected from it
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mut source = MockDataSource {
+            zones: vec![("Office", zone_state(21.0, 19.0, "2022-09-03T17:40:00.000Z"))],
+            weather: None,
+        };
+        let mut collector = Collector::new(false).with_temperature_bounds(-50.0, 80.0);
+
+        // WHEN
+        collector.collect(&mut source).await;
+
+        source.zones = vec![(
+            "Office",
+            zone_state(21.0, -60.0, "2022-09-03T17:50:00.000Z"),
+        )];
+        collector.collect(&mut source).await;
+        let rejected_state = state::read(|state| {
+            state.zones[0]
+                .state_response
+                .sensorDataPoints
+                .as_ref()
+                .map(|sensor_data_points| sensor_data_points.insideTemperature.is_some())
+        });
+
+        source.zones = vec![("Office", zone_state(21.0, 19.5, "2022-09-03T18:00:00.000Z"))];
+        collector.collect(&mut source).await;
+        let output = render().await;
+
+        // THEN
+        assert_eq!(rejected_state, Some(false));
+        assert!(!output.contains("tado_zone_time_to_target_seconds{"));
+        assert!(output
+            .lines()
+            .any(|l| l == r#"tado_zone_rejected_readings_total{zone="Office"} 1"#));
+    }
+
+    fn heating_power_zone_state(heating_power: f64) -> String {
+        format!(
+            r#"{{
+                "setting": {{
+                    "type": "HEATING",
+                    "temperature": {{ "celsius": 21.0, "fahrenheit": 69.8 }}
+                }},
+                "activityDataPoints": {{
+                    "heatingPower": {{ "percentage": {} }}
+                }},
+                "sensorDataPoints": {{}}
+            }}"#,
+            heating_power
+        )
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_valve_open() {
+        /*
+        GIVEN a zone whose heating power hovers around the thresholds of its
+              valves, opening at 5% and closing at 1%
+        WHEN collecting after each heating power change
+        THEN the valves only open once the opening threshold is reached, and
+             only close once the closing threshold is reached
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let heating_powers = [0.0, 3.0, 4.9, 5.0, 3.0, 1.1, 6.0, 1.0, 2.0, 0.0];
+        let mut source = MockDataSource {
+            zones: Vec::new(),
+            weather: None,
+        };
+        let mut collector = Collector::new(false).with_valve_thresholds(5.0, 1.0);
+
+        // WHEN
+        let mut actual = Vec::new();
+        for heating_power in heating_powers {
+            source.zones = vec![("Office", heating_power_zone_state(heating_power))];
+            collector.collect(&mut source).await;
+
+            let output = render().await;
+            actual.push(
+                output
+                    .lines()
+                    .any(|l| l == r#"tado_zone_valve_open{zone="Office"} 1"#),
+            );
+        }
+
+        // THEN
+        assert_eq!(
+            actual,
+            vec![false, false, false, true, true, true, true, false, false, false]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_summary() {
+        /*
+        GIVEN a home with two zones and the weather, retrieved with retries
+        WHEN collecting, then collecting again while the zones can't be
+             retrieved
+        THEN each scrape is summed up in a single line, with the number of
+             zones, the weather status, the retries and the failures
+        */
+
+        let _registry = lock_registry();
+        let logs = logs::capture();
+
+        // GIVEN
+        let mut source = RetriedDataSource {
+            source: MockDataSource {
+                zones: vec![
+                    ("Office", zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z")),
+                    ("Attic", zone_state(18.0, 16.0, "2022-09-03T17:43:41.088Z")),
+                ],
+                weather: Some(weather(10.0, "2022-09-03T17:30:00.000Z")),
+            },
+            retries: 7,
+        };
+        let mut collector = Collector::new(false).with_clock(Arc::new(FakeClock::default()));
+
+        // WHEN
+        collector.collect(&mut source).await;
+
+        source.source.zones.clear();
+        source.source.weather = None;
+        collector.collect(&mut source).await;
+
+        // THEN
+        let summaries: Vec<String> = logs
+            .lines()
+            .into_iter()
+            .filter(|line| line.starts_with("scrape ") && line.contains(" retries=7 "))
+            .collect();
+
+        assert_eq!(
+            summaries,
+            vec![
+                "scrape succeeded: zones=2 unsupported_zones=0 weather=ok retries=7 consecutive_failures=0 duration=0.000s",
+                "scrape failed: zones=0 unsupported_zones=0 weather=unavailable retries=7 consecutive_failures=1 duration=0.000s",
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_collect_removed_zone() {
+        /*
+        GIVEN two zones, one of which gets removed from the home
+        WHEN collecting before and after the removal, then once its grace
+             period is over
+        THEN the removed zone is no longer reported, except for its counters
+             until the end of the grace period
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let clock = Arc::new(FakeClock::default());
+        let mut source = MockDataSource {
+            zones: vec![
+                ("Office", zone_state(21.0, 20.0, "2022-09-03T17:43:41.088Z")),
+                ("Attic", zone_state(18.0, 16.0, "2022-09-03T17:43:41.088Z")),
+            ],
+            weather: None,
+        };
+        let mut collector = Collector::new(false)
+            .with_zone_eviction_grace(Duration::from_secs(3600))
+            .with_clock(clock.clone());
+
+        // WHEN
+        collector.collect(&mut source).await;
+        let before = render().await;
+
+        source.zones.pop();
+        clock.advance(Duration::from_secs(60));
+        collector.collect(&mut source).await;
+        let removed = render().await;
+
+        clock.advance(Duration::from_secs(3600));
+        collector.collect(&mut source).await;
+        let evicted = render().await;
+
+        // THEN
+        assert!(before.contains(r#"zone="Attic""#));
+
+        let attic_series: Vec<&str> = removed
+            .lines()
+            .filter(|line| line.contains(r#"zone="Attic""#))
+            .collect();
+        assert_eq!(
+            attic_series,
+            vec![r#"tado_zone_heating_seconds_total{zone="Attic"} 0"#]
+        );
+
+        assert!(!evicted.contains(r#"zone="Attic""#));
+        assert!(evicted.contains(r#"zone="Office""#));
+    }
+}
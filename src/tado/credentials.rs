@@ -0,0 +1,73 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// A source of the credentials of the tado° account, read again periodically
+/// so rotated secrets are picked up without restarting the exporter.
+pub trait CredentialProvider: Send + Sync {
+    fn credentials(&self) -> io::Result<Credentials>;
+}
+
+/// Read the password from a file, e.g. a secret mounted by the orchestrator.
+pub struct FileCredentials {
+    username: String,
+    password_path: PathBuf,
+}
+
+impl FileCredentials {
+    pub fn new(username: String, password_path: PathBuf) -> FileCredentials {
+        FileCredentials {
+            username,
+            password_path,
+        }
+    }
+}
+
+impl CredentialProvider for FileCredentials {
+    fn credentials(&self) -> io::Result<Credentials> {
+        let password = fs::read_to_string(&self.password_path)?;
+
+        Ok(Credentials {
+            username: self.username.clone(),
+            // secrets are often written with a trailing newline
+            password: password.trim_end_matches(['\r', '\n']).to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_credentials() {
+        /*
+        GIVEN a password file ending with a newline
+        WHEN reading the credentials
+        THEN the password is read without the newline
+        */
+
+        // GIVEN
+        let path = std::env::temp_dir().join(format!("tado-password-{}", std::process::id()));
+        fs::write(&path, "123Password!\n").unwrap();
+
+        // WHEN
+        let actual = FileCredentials::new("test-user".to_string(), path.clone()).credentials();
+        fs::remove_file(&path).unwrap();
+
+        // THEN
+        assert_eq!(
+            actual.unwrap(),
+            Credentials {
+                username: "test-user".to_string(),
+                password: "123Password!".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::convert::Infallible;
+use std::sync::RwLock;
+
+use chrono::Utc;
+use hyper::{header::CONTENT_TYPE, Body, Request, Response};
+use lazy_static::lazy_static;
+use serde_derive::Serialize;
+
+/// The internal state of the exporter, as exposed on `/debug/state` to help
+/// figuring out why metrics are missing.
+///
+/// Only plain status fields are stored here: tokens and credentials have no
+/// field to end up in, so they can never be dumped.
+#[derive(Serialize, Default)]
+pub struct DebugState {
+    pub client: Option<ClientState>,
+    pub last_scrape: Option<ScrapeResult>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ClientState {
+    pub home_id: i32,
+    pub time_zone: Option<String>,
+    pub access_token_valid: bool,
+    // Seconds until the access token gets renewed, if there is one.
+    pub access_token_renewal_in_seconds: Option<u64>,
+    // The number of zones listed during the latest scrape.
+    pub zone_count: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScrapeResult {
+    pub success: bool,
+    pub timestamp: String, // RFC 3339 timestamp
+}
+
+lazy_static! {
+    static ref DEBUG_STATE: RwLock<DebugState> = RwLock::new(DebugState::default());
+}
+
+pub fn set_client_state(client: ClientState) {
+    DEBUG_STATE.write().unwrap().client = Some(client);
+}
+
+pub fn set_last_scrape(success: bool) {
+    DEBUG_STATE.write().unwrap().last_scrape = Some(ScrapeResult {
+        success,
+        timestamp: Utc::now().to_rfc3339(),
+    });
+}
+
+pub async fn renderer(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let buffer = serde_json::to_vec(&*DEBUG_STATE.read().unwrap()).unwrap();
+
+    let response = Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(buffer))
+        .unwrap();
+
+    Ok(response)
+}
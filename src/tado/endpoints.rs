@@ -0,0 +1,185 @@
+/// Path templates of the tado° API endpoints, resolved against the client's
+/// base URL.
+///
+/// Templates may reference the `{home_id}`, `{zone_id}` and `{timetable_id}`
+/// placeholders, so individual endpoints can be redirected (e.g. to a proxy or
+/// a mock) or adjusted if tado° moves them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    pub me: String,
+    pub home: String,
+    pub zones: String,
+    pub zone_state: String,
+    pub weather: String,
+    pub devices: String,
+    pub active_timetable: String,
+    pub timetable_blocks: String,
+    pub away_configuration: String,
+    pub mobile_devices: String,
+    pub home_state: String,
+    pub day_report: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Endpoints {
+        Endpoints {
+            me: "/api/v2/me".to_string(),
+            home: "/api/v2/homes/{home_id}".to_string(),
+            zones: "/api/v2/homes/{home_id}/zones".to_string(),
+            zone_state: "/api/v2/homes/{home_id}/zones/{zone_id}/state".to_string(),
+            weather: "/api/v2/homes/{home_id}/weather".to_string(),
+            devices: "/api/v2/homes/{home_id}/devices".to_string(),
+            active_timetable: "/api/v2/homes/{home_id}/zones/{zone_id}/schedule/activeTimetable"
+                .to_string(),
+            timetable_blocks:
+                "/api/v2/homes/{home_id}/zones/{zone_id}/schedule/timetables/{timetable_id}/blocks"
+                    .to_string(),
+            away_configuration:
+                "/api/v2/homes/{home_id}/zones/{zone_id}/schedule/awayConfiguration".to_string(),
+            mobile_devices: "/api/v2/homes/{home_id}/mobileDevices".to_string(),
+            home_state: "/api/v2/homes/{home_id}/state".to_string(),
+            day_report: "/api/v2/homes/{home_id}/zones/{zone_id}/dayReport".to_string(),
+        }
+    }
+}
+
+impl Endpoints {
+    pub fn me(&self) -> String {
+        self.me.clone()
+    }
+
+    pub fn home(&self, home_id: i32) -> String {
+        resolve(&self.home, &[("{home_id}", home_id)])
+    }
+
+    pub fn zones(&self, home_id: i32) -> String {
+        resolve(&self.zones, &[("{home_id}", home_id)])
+    }
+
+    pub fn zone_state(&self, home_id: i32, zone_id: i32) -> String {
+        resolve(
+            &self.zone_state,
+            &[("{home_id}", home_id), ("{zone_id}", zone_id)],
+        )
+    }
+
+    pub fn weather(&self, home_id: i32) -> String {
+        resolve(&self.weather, &[("{home_id}", home_id)])
+    }
+
+    pub fn devices(&self, home_id: i32) -> String {
+        resolve(&self.devices, &[("{home_id}", home_id)])
+    }
+
+    pub fn active_timetable(&self, home_id: i32, zone_id: i32) -> String {
+        resolve(
+            &self.active_timetable,
+            &[("{home_id}", home_id), ("{zone_id}", zone_id)],
+        )
+    }
+
+    pub fn timetable_blocks(&self, home_id: i32, zone_id: i32, timetable_id: i32) -> String {
+        resolve(
+            &self.timetable_blocks,
+            &[
+                ("{home_id}", home_id),
+                ("{zone_id}", zone_id),
+                ("{timetable_id}", timetable_id),
+            ],
+        )
+    }
+
+    pub fn away_configuration(&self, home_id: i32, zone_id: i32) -> String {
+        resolve(
+            &self.away_configuration,
+            &[("{home_id}", home_id), ("{zone_id}", zone_id)],
+        )
+    }
+
+    pub fn mobile_devices(&self, home_id: i32) -> String {
+        resolve(&self.mobile_devices, &[("{home_id}", home_id)])
+    }
+
+    pub fn home_state(&self, home_id: i32) -> String {
+        resolve(&self.home_state, &[("{home_id}", home_id)])
+    }
+
+    pub fn day_report(&self, home_id: i32, zone_id: i32) -> String {
+        resolve(
+            &self.day_report,
+            &[("{home_id}", home_id), ("{zone_id}", zone_id)],
+        )
+    }
+}
+
+/// Replace the `{home_id}` placeholder of an arbitrary path template.
+pub fn resolve_home(template: &str, home_id: i32) -> String {
+    resolve(template, &[("{home_id}", home_id)])
+}
+
+fn resolve(template: &str, placeholders: &[(&str, i32)]) -> String {
+    placeholders
+        .iter()
+        .fold(template.to_string(), |path, (placeholder, value)| {
+            path.replace(placeholder, &value.to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tado::client::BASE_URL;
+
+    use rstest::*;
+
+    #[rstest(path, expected,
+        case(Endpoints::default().me(), "https://my.tado.com/api/v2/me"),
+        case(Endpoints::default().home(1234), "https://my.tado.com/api/v2/homes/1234"),
+        case(Endpoints::default().zones(1234), "https://my.tado.com/api/v2/homes/1234/zones"),
+        case(
+            Endpoints::default().zone_state(1234, 5),
+            "https://my.tado.com/api/v2/homes/1234/zones/5/state"
+        ),
+        case(Endpoints::default().weather(1234), "https://my.tado.com/api/v2/homes/1234/weather"),
+        case(Endpoints::default().devices(1234), "https://my.tado.com/api/v2/homes/1234/devices"),
+        case(
+            Endpoints::default().active_timetable(1234, 5),
+            "https://my.tado.com/api/v2/homes/1234/zones/5/schedule/activeTimetable"
+        ),
+        case(
+            Endpoints::default().timetable_blocks(1234, 5, 1),
+            "https://my.tado.com/api/v2/homes/1234/zones/5/schedule/timetables/1/blocks"
+        ),
+        case(
+            Endpoints::default().away_configuration(1234, 5),
+            "https://my.tado.com/api/v2/homes/1234/zones/5/schedule/awayConfiguration"
+        ),
+        case(
+            Endpoints::default().mobile_devices(1234),
+            "https://my.tado.com/api/v2/homes/1234/mobileDevices"
+        ),
+        case(
+            Endpoints::default().home_state(1234),
+            "https://my.tado.com/api/v2/homes/1234/state"
+        ),
+        case(
+            Endpoints::default().day_report(1234, 5),
+            "https://my.tado.com/api/v2/homes/1234/zones/5/dayReport"
+        ),
+    )]
+    fn test_default_endpoints(path: String, expected: &str) {
+        assert_eq!(BASE_URL.join(&path).unwrap().as_str(), expected);
+    }
+
+    #[test]
+    fn test_overridden_endpoint() {
+        let endpoints = Endpoints {
+            weather: "/proxy/weather/{home_id}".to_string(),
+            ..Endpoints::default()
+        };
+
+        assert_eq!(endpoints.weather(1234), "/proxy/weather/1234");
+        assert_eq!(endpoints.zones(1234), "/api/v2/homes/1234/zones");
+    }
+}
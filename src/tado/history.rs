@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use hyper::{header::CONTENT_TYPE, Body, Request, Response, StatusCode};
+use lazy_static::lazy_static;
+use serde_derive::Serialize;
+
+use super::homeassistant::{HomeAssistantState, HomeAssistantWeather, HomeAssistantZone};
+use super::state::State;
+
+lazy_static! {
+    static ref HISTORY: RwLock<Option<Arc<History>>> = RwLock::new(None);
+}
+
+/// The values of a scrape, in the flat shape of `/homeassistant.json`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub timestamp: String, // RFC 3339 timestamp
+    pub zones: BTreeMap<String, HomeAssistantZone>,
+    pub weather: Option<HomeAssistantWeather>,
+}
+
+impl Snapshot {
+    pub fn new(state: &State, at: DateTime<Utc>) -> Snapshot {
+        let HomeAssistantState { zones, weather, .. } = HomeAssistantState::from(state);
+
+        Snapshot {
+            timestamp: at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            zones,
+            weather,
+        }
+    }
+}
+
+/// The most recent scrapes, as exposed on `/history.json` to graph basic
+/// trends without a Prometheus server.
+pub struct History {
+    capacity: usize,
+    snapshots: Mutex<VecDeque<Snapshot>>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History {
+            capacity,
+            snapshots: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Keep `snapshot`, dropping the oldest one once the buffer is full.
+    pub fn push(&self, snapshot: Snapshot) {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        if snapshots.len() == self.capacity {
+            snapshots.pop_front();
+        }
+        snapshots.push_back(snapshot);
+    }
+
+    /// The snapshots kept, oldest first.
+    pub fn snapshots(&self) -> Vec<Snapshot> {
+        self.snapshots.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Keep the last `capacity` scrapes for `/history.json`. Nothing is kept when
+/// 0.
+pub fn init(capacity: usize) {
+    *HISTORY.write().unwrap() = (capacity > 0).then(|| Arc::new(History::new(capacity)));
+}
+
+/// Keep the values of the scrape ending at `at`, if enabled.
+pub fn record(state: &State, at: DateTime<Utc>) {
+    if let Some(history) = &*HISTORY.read().unwrap() {
+        history.push(Snapshot::new(state, at));
+    }
+}
+
+pub async fn renderer(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = match &*HISTORY.read().unwrap() {
+        Some(history) => Response::builder()
+            .status(200)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::to_vec(&*history.snapshots.lock().unwrap()).unwrap(),
+            ))
+            .unwrap(),
+        // the history is opt-in, as if the endpoint didn't exist otherwise
+        None => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+    };
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tado::model::ZoneStateResponse;
+
+    #[test]
+    fn test_history_capacity() {
+        /*
+        GIVEN a history keeping 3 snapshots
+        WHEN 5 scrapes are recorded
+        THEN the last 3 are kept, oldest first, and the others are evicted
+        */
+
+        // GIVEN
+        let history = History::new(3);
+        let state = State {
+            zones: vec![ZoneStateResponse {
+                name: "Office".to_string(),
+                state_response: serde_json::from_str(
+                    r#"{
+                        "setting": {
+                            "type": "HEATING",
+                            "temperature": { "celsius": 21.0, "fahrenheit": 69.8 }
+                        },
+                        "sensorDataPoints": {
+                            "insideTemperature": { "celsius": 20.5, "fahrenheit": 68.9 },
+                            "humidity": { "percentage": 48.2 }
+                        }
+                    }"#,
+                )
+                .unwrap(),
+                scheduled_setting: None,
+                away_setting: None,
+            }],
+            weather: None,
+        };
+        let started_at = "2024-01-14T18:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        // WHEN
+        for scrape in 0..5 {
+            history.push(Snapshot::new(
+                &state,
+                started_at + chrono::Duration::seconds(10 * scrape),
+            ));
+        }
+
+        // THEN
+        let snapshots = history.snapshots();
+        let timestamps: Vec<&str> = snapshots
+            .iter()
+            .map(|snapshot| snapshot.timestamp.as_str())
+            .collect();
+
+        assert_eq!(
+            timestamps,
+            vec![
+                "2024-01-14T18:00:20Z",
+                "2024-01-14T18:00:30Z",
+                "2024-01-14T18:00:40Z"
+            ]
+        );
+        assert_eq!(snapshots[0].zones["Office"].temperature, Some(20.5));
+        assert_eq!(snapshots[0].zones["Office"].setpoint, Some(21.0));
+    }
+}
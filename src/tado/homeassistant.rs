@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+
+use hyper::{header::CONTENT_TYPE, Body, Request, Response};
+use serde_derive::Serialize;
+
+use super::state::{self, State};
+
+// The version of the document shape, bumped on changes breaking sensors
+// reading it.
+const VERSION: u32 = 1;
+
+/// The data retrieved during the latest scrape, as exposed on
+/// `/homeassistant.json`: a flat and stable shape a Home Assistant REST
+/// sensor can read directly, unlike `/state.json` which mirrors the API.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct HomeAssistantState {
+    pub version: u32,
+    // Keyed by name, for templates such as `value_json.zones.Office`.
+    pub zones: BTreeMap<String, HomeAssistantZone>,
+    pub weather: Option<HomeAssistantWeather>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HomeAssistantZone {
+    pub temperature: Option<f64>, // celsius
+    pub humidity: Option<f64>,    // percentage
+    pub setpoint: Option<f64>,    // celsius, absent while the zone is off
+    pub heating: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HomeAssistantWeather {
+    pub outside_temperature: f64, // celsius
+    pub solar_intensity: f64,     // percentage
+    pub state: Option<String>,    // e.g. SUN, CLOUDY_PARTLY, RAIN
+}
+
+impl From<&State> for HomeAssistantState {
+    fn from(state: &State) -> HomeAssistantState {
+        let zones = state
+            .zones
+            .iter()
+            .map(|zone| {
+                let response = &zone.state_response;
+                let sensors = response.sensorDataPoints.as_ref();
+
+                (
+                    zone.name.clone(),
+                    HomeAssistantZone {
+                        temperature: sensors
+                            .and_then(|sensors| sensors.insideTemperature.as_ref())
+                            .map(|temperature| temperature.celsius),
+                        humidity: sensors
+                            .and_then(|sensors| sensors.humidity.as_ref())
+                            .map(|humidity| humidity.percentage),
+                        setpoint: response
+                            .setting
+                            .temperature
+                            .as_ref()
+                            .map(|temperature| temperature.celsius),
+                        heating: response.is_heating(),
+                    },
+                )
+            })
+            .collect();
+
+        let weather = state.weather.as_ref().map(|weather| HomeAssistantWeather {
+            outside_temperature: weather.outsideTemperature.celsius,
+            solar_intensity: weather.solarIntensity.percentage,
+            state: weather
+                .weatherState
+                .as_ref()
+                .map(|weather_state| weather_state.value.clone()),
+        });
+
+        HomeAssistantState {
+            version: VERSION,
+            zones,
+            weather,
+        }
+    }
+}
+
+pub async fn renderer(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let buffer = state::read(|state| serde_json::to_vec(&HomeAssistantState::from(state)).unwrap());
+
+    let response = Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(buffer))
+        .unwrap();
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tado::metrics::lock_registry;
+    use crate::tado::model::ZoneStateResponse;
+
+    use hyper::body;
+    use serde_json::{json, Value};
+
+    #[allow(clippy::await_holding_lock)]
+    #[actix_rt::test]
+    async fn test_renderer() {
+        /*
+        GIVEN a stored state with a heating zone, a zone switched off and
+              weather data
+        WHEN the Home Assistant renderer is called
+        THEN the JSON document holds the version, and the readings of the
+             zones and weather in a flat shape
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let zone = |name: &str, state: &str| ZoneStateResponse {
+            name: name.to_string(),
+            state_response: serde_json::from_str(state).unwrap(),
+            scheduled_setting: None,
+            away_setting: None,
+        };
+
+        state::set_state(
+            vec![
+                zone(
+                    "Office",
+                    r#"{
+                        "setting": {
+                            "type": "HEATING",
+                            "power": "ON",
+                            "temperature": { "celsius": 21.0, "fahrenheit": 69.8 }
+                        },
+                        "activityDataPoints": {
+                            "heatingPower": { "percentage": 12.0 }
+                        },
+                        "sensorDataPoints": {
+                            "insideTemperature": { "celsius": 20.5, "fahrenheit": 68.9 },
+                            "humidity": { "percentage": 48.2 }
+                        }
+                    }"#,
+                ),
+                zone(
+                    "Guest room",
+                    r#"{
+                        "setting": { "type": "HEATING", "power": "OFF", "temperature": null },
+                        "sensorDataPoints": {
+                            "insideTemperature": { "celsius": 17.2, "fahrenheit": 62.96 }
+                        }
+                    }"#,
+                ),
+            ],
+            Some(
+                serde_json::from_str(
+                    r#"{
+                        "solarIntensity": { "type": "PERCENTAGE", "percentage": 18.3 },
+                        "outsideTemperature": {
+                            "type": "TEMPERATURE",
+                            "celsius": 10.0,
+                            "fahrenheit": 50.0
+                        },
+                        "weatherState": { "type": "WEATHER_STATE", "value": "CLOUDY_PARTLY" }
+                    }"#,
+                )
+                .unwrap(),
+            ),
+        );
+
+        // WHEN
+        let response = renderer(Request::new(Body::empty())).await.unwrap();
+
+        // THEN
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+
+        let actual: Value =
+            serde_json::from_slice(&body::to_bytes(response.into_body()).await.unwrap()).unwrap();
+
+        assert_eq!(
+            actual,
+            json!({
+                "version": 1,
+                "zones": {
+                    "Guest room": {
+                        "temperature": 17.2,
+                        "humidity": null,
+                        "setpoint": null,
+                        "heating": false
+                    },
+                    "Office": {
+                        "temperature": 20.5,
+                        "humidity": 48.2,
+                        "setpoint": 21.0,
+                        "heating": true
+                    }
+                },
+                "weather": {
+                    "outside_temperature": 10.0,
+                    "solar_intensity": 18.3,
+                    "state": "CLOUDY_PARTLY"
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::{SecondsFormat, Utc};
+use env_logger::{Builder as LoggerBuilder, Logger};
+use hyper::{header::CONTENT_TYPE, Body, Request, Response, StatusCode};
+use lazy_static::lazy_static;
+use log::{Log, Metadata, Record, SetLoggerError};
+
+const REDACTED: &str = "<redacted>";
+
+// The keys whose values are never kept in the buffer, whether they appear in
+// a form body (`password=...`), a JSON document or a Debug output.
+const SECRET_KEYS: [&str; 6] = [
+    "password",
+    "client_secret",
+    "access_token",
+    "refresh_token",
+    "authorization",
+    "cookie",
+];
+
+// The characters ending the value of a secret.
+const VALUE_DELIMITERS: &[u8] = b"&\"', ;}\r\n";
+
+// Cookie headers hold several `name=value` pairs, all of them redacted.
+const COOKIE_DELIMITERS: &[u8] = b"\"\r\n";
+
+lazy_static! {
+    static ref LOG_BUFFER: RwLock<Option<Arc<LogBuffer>>> = RwLock::new(None);
+}
+
+/// The most recent log lines, as exposed on `/debug/logs` to share them
+/// without reconfiguring the logging of a container.
+pub struct LogBuffer {
+    capacity: usize,
+    lines: Mutex<VecDeque<String>>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> LogBuffer {
+        LogBuffer {
+            capacity,
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Keep `line`, dropping the oldest one once the buffer is full.
+    pub fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// A logger writing to `env_logger` as usual, while also keeping the records
+/// it lets through in a buffer, with their secrets redacted.
+pub struct BufferedLogger {
+    inner: Logger,
+    buffer: Arc<LogBuffer>,
+}
+
+impl BufferedLogger {
+    pub fn new(inner: Logger, buffer: Arc<LogBuffer>) -> BufferedLogger {
+        BufferedLogger { inner, buffer }
+    }
+}
+
+impl Log for BufferedLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+
+        self.inner.log(record);
+        self.buffer.push(redact(&format!(
+            "[{} {:<5} {}] {}",
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            record.level(),
+            record.target(),
+            record.args()
+        )));
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the logger built by `builder`, keeping the last `capacity` log
+/// lines for `/debug/logs`. Lines are only logged as usual when 0.
+pub fn init(mut builder: LoggerBuilder, capacity: usize) -> Result<(), SetLoggerError> {
+    if capacity == 0 {
+        return builder.try_init();
+    }
+
+    let inner = builder.build();
+    let max_level = inner.filter();
+    let buffer = Arc::new(LogBuffer::new(capacity));
+
+    log::set_boxed_logger(Box::new(BufferedLogger::new(inner, buffer.clone())))?;
+    log::set_max_level(max_level);
+    *LOG_BUFFER.write().unwrap() = Some(buffer);
+
+    Ok(())
+}
+
+/// Replace the values of secrets, such as passwords and tokens, in `line`.
+pub fn redact(line: &str) -> String {
+    // only ASCII characters are lowercased, byte offsets are kept
+    let lower = line.to_ascii_lowercase();
+    let lower = lower.as_bytes();
+
+    let mut redacted = String::with_capacity(line.len());
+    let mut copied = 0;
+    let mut pos = 0;
+
+    while pos < lower.len() {
+        match secret_value_at(lower, pos) {
+            Some((start, end)) => {
+                redacted.push_str(&line[copied..start]);
+                redacted.push_str(REDACTED);
+                copied = end;
+                pos = end;
+            }
+            None => pos += 1,
+        }
+    }
+    redacted.push_str(&line[copied..]);
+
+    redacted
+}
+
+// The byte range of the value of the secret starting at `pos`, if any.
+fn secret_value_at(lower: &[u8], pos: usize) -> Option<(usize, usize)> {
+    let skip = |mut i: usize, chars: &[u8]| {
+        while i < lower.len() && chars.contains(&lower[i]) {
+            i += 1;
+        }
+        i
+    };
+
+    let mut delimiters = VALUE_DELIMITERS;
+    let mut i = if lower[pos..].starts_with(b"bearer ") {
+        skip(pos + "bearer".len(), b" ")
+    } else {
+        let key = SECRET_KEYS
+            .iter()
+            .find(|key| lower[pos..].starts_with(key.as_bytes()))?;
+        if *key == "cookie" {
+            delimiters = COOKIE_DELIMITERS;
+        }
+
+        // the key must be followed by its value, e.g. `password=` or `"password": "`
+        let i = skip(pos + key.len(), b"\"' ");
+        if i == lower.len() || (lower[i] != b'=' && lower[i] != b':') {
+            return None;
+        }
+
+        let i = skip(i + 1, b"\"' ");
+        if lower[i..].starts_with(b"bearer ") {
+            skip(i + "bearer".len(), b" ")
+        } else {
+            i
+        }
+    };
+
+    let start = i;
+    while i < lower.len() && !delimiters.contains(&lower[i]) {
+        i += 1;
+    }
+
+    if i == start {
+        None
+    } else {
+        Some((start, i))
+    }
+}
+
+/// Install a logger keeping every log line as is, for tests to assert on what
+/// gets logged. The logger is only installed once per process.
+#[cfg(test)]
+pub fn capture() -> Arc<LogBuffer> {
+    struct CapturingLogger(Arc<LogBuffer>);
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= log::Level::Info
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                self.0.push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    lazy_static! {
+        static ref CAPTURED: Arc<LogBuffer> = {
+            let buffer = Arc::new(LogBuffer::new(100_000));
+            log::set_boxed_logger(Box::new(CapturingLogger(buffer.clone()))).unwrap();
+            log::set_max_level(log::LevelFilter::Info);
+            buffer
+        };
+    }
+
+    CAPTURED.clone()
+}
+
+pub async fn renderer(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = match &*LOG_BUFFER.read().unwrap() {
+        Some(buffer) => Response::builder()
+            .status(200)
+            .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(Body::from(buffer.lines().join("\n")))
+            .unwrap(),
+        // the buffer is opt-in, as if the endpoint didn't exist otherwise
+        None => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+    };
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use log::Level;
+    use rstest::*;
+
+    #[rstest(line, expected,
+        case(
+            "client_id=tado-web-app&grant_type=password&scope=home.user&username=test-user&password=123Password!&client_secret=123-secret",
+            "client_id=tado-web-app&grant_type=password&scope=home.user&username=test-user&password=<redacted>&client_secret=<redacted>"
+        ),
+        case(
+            r#"{"access_token":"eyJhbGciOi","token_type":"bearer","refresh_token":"abc-123"}"#,
+            r#"{"access_token":"<redacted>","token_type":"bearer","refresh_token":"<redacted>"}"#
+        ),
+        case(
+            r#"AuthApiResponse { access_token: "eyJhbGciOi", expires_in: 599 }"#,
+            r#"AuthApiResponse { access_token: "<redacted>", expires_in: 599 }"#
+        ),
+        case(
+            "Authorization: Bearer eyJhbGciOi",
+            "Authorization: Bearer <redacted>"
+        ),
+        case(
+            r#"{"cookie": "session=abc-123; csrf=def-456", "accept": "*/*"}"#,
+            r#"{"cookie": "<redacted>", "accept": "*/*"}"#
+        ),
+        case(
+            "sending request with bearer eyJhbGciOi",
+            "sending request with bearer <redacted>"
+        ),
+        case(
+            "unable to read the password file: No such file or directory",
+            "unable to read the password file: No such file or directory"
+        ),
+        case(
+            r#"{"access_token_valid":true,"zone_count":4}"#,
+            r#"{"access_token_valid":true,"zone_count":4}"#
+        ),
+    )]
+    fn test_redact(line: &str, expected: &str) {
+        assert_eq!(redact(line), expected);
+    }
+
+    #[test]
+    fn test_buffered_logger() {
+        /*
+        GIVEN a logger keeping the last 2 log lines
+        WHEN logging 3 lines, one of them containing a token, and a debug
+             line below the logging level
+        THEN the last 2 info lines are kept, with the token redacted
+        */
+
+        // GIVEN
+        let buffer = Arc::new(LogBuffer::new(2));
+        let inner = LoggerBuilder::new()
+            .filter_level(log::LevelFilter::Info)
+            .build();
+        let logger = BufferedLogger::new(inner, buffer.clone());
+
+        // WHEN
+        for (level, line) in [
+            (Level::Info, "starting tado° exporter"),
+            (Level::Info, "authenticating with password=123Password!"),
+            (Level::Debug, "retrieving zones ..."),
+            (Level::Info, "received access_token=eyJhbGciOi"),
+        ] {
+            logger.log(
+                &Record::builder()
+                    .level(level)
+                    .target("tado_exporter::tado::client")
+                    .args(format_args!("{}", line))
+                    .build(),
+            );
+        }
+
+        // THEN
+        let lines = buffer.lines();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(
+            "INFO  tado_exporter::tado::client] authenticating with password=<redacted>"
+        ));
+        assert!(lines[1]
+            .ends_with("INFO  tado_exporter::tado::client] received access_token=<redacted>"));
+        assert!(lines
+            .iter()
+            .all(|line| !line.contains("123Password!") && !line.contains("eyJhbGciOi")));
+    }
+}
@@ -1,13 +1,84 @@
+use std::collections::HashMap;
 use std::convert::Infallible;
+use std::str::FromStr;
+use std::sync::RwLock;
 
-use super::model::{WeatherApiResponse, ZoneStateResponse};
+use chrono::{DateTime, Utc};
+
+use super::model::{
+    DeviceZoneResponse, GeolocationApiResponse, HomeStateApiResponse, MobileDeviceApiResponse,
+    UnsupportedZoneResponse, WeatherApiResponse, ZoneStateResponse, ZonesApiResponse,
+};
 
 use hyper::{header::CONTENT_TYPE, Body, Request, Response};
 use lazy_static::lazy_static;
-use log::info;
-use prometheus::{Encoder, GaugeVec, TextEncoder};
+use log::{debug, warn};
+use prometheus::{CounterVec, Encoder, GaugeVec, HistogramVec, IntCounterVec, TextEncoder};
+
+/// The prefix of the metrics names, as they are registered.
+pub const DEFAULT_NAMESPACE: &str = "tado";
+
+/// What identifies zones in the `zone` label of the metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoneLabel {
+    /// The name of the zone, as set in the tado° app.
+    Name,
+    /// The identifier of the zone, which is kept when the zone is renamed.
+    Id,
+}
+
+impl ZoneLabel {
+    pub fn of(&self, zone: &ZonesApiResponse) -> String {
+        match self {
+            ZoneLabel::Name => zone.name.clone(),
+            ZoneLabel::Id => zone.id.to_string(),
+        }
+    }
+}
+
+impl FromStr for ZoneLabel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ZoneLabel, String> {
+        match s {
+            "name" => Ok(ZoneLabel::Name),
+            "id" => Ok(ZoneLabel::Id),
+            _ => Err(format!("unknown zone label: {}, expected name or id", s)),
+        }
+    }
+}
+
+/// The metrics to render, by their name with the `tado` prefix. A name
+/// ending with `*` matches every metric starting with the rest of it, e.g.
+/// `tado_weather_*`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricFilter {
+    /// Render only these metrics, or all of them when empty.
+    pub allow: Vec<String>,
+    /// Never render these metrics, even if allowed.
+    pub deny: Vec<String>,
+}
+
+impl MetricFilter {
+    pub fn allows(&self, name: &str) -> bool {
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        };
+
+        (self.allow.is_empty() || self.allow.iter().any(matches)) && !self.deny.iter().any(matches)
+    }
+}
 
 lazy_static! {
+    static ref NAMESPACE: RwLock<String> = RwLock::new(DEFAULT_NAMESPACE.to_string());
+    static ref METRIC_FILTER: RwLock<MetricFilter> = RwLock::new(MetricFilter::default());
+    pub static ref BUILD_INFO: GaugeVec = register_gauge_vec!(
+        "tado_exporter_build_info",
+        "1 for the version of the exporter, the git commit and rustc version it was built from.",
+        &["version", "git_sha", "rustc"]
+    )
+    .unwrap();
     pub static ref ACTIVITY_HEATING_POWER: GaugeVec = register_gauge_vec!(
         "tado_activity_heating_power_percentage",
         "The % of heating power in a specific zone.",
@@ -32,6 +103,12 @@ lazy_static! {
         &["zone", "type", "unit"]
     )
     .unwrap();
+    pub static ref ZONE_INSIDE_TEMPERATURE_APP: GaugeVec = register_gauge_vec!(
+        "tado_zone_inside_temperature_app_celsius",
+        "The inside temperature of a specific zone, rounded to 0.1 celsius like the tado° app shows it.",
+        &["zone"]
+    )
+    .unwrap();
     pub static ref SENSOR_HUMIDITY_PERCENTAGE: GaugeVec = register_gauge_vec!(
         "tado_sensor_humidity_percentage",
         "The % of humidity in a specific zone.",
@@ -44,34 +121,336 @@ lazy_static! {
         &[]
     )
     .unwrap();
+    pub static ref WEATHER_SOLAR_INTENSITY_RATIO: GaugeVec = register_gauge_vec!(
+        "tado_weather_solar_intensity_ratio",
+        "Solar intensity outside the house, scaled from 0 to 1.",
+        &[]
+    )
+    .unwrap();
+    pub static ref WEATHER_STATE: GaugeVec = register_gauge_vec!(
+        "tado_weather_state",
+        "1 for the current weather state outside the house.",
+        &["type", "value"]
+    )
+    .unwrap();
+    pub static ref WEATHER_IS_DAYLIGHT: GaugeVec = register_gauge_vec!(
+        "tado_weather_is_daylight",
+        "1 if the sun is up at the house, 0 otherwise.",
+        &[]
+    )
+    .unwrap();
     pub static ref WEATHER_OUTSIDE_TEMPERATURE: GaugeVec = register_gauge_vec!(
         "weather_outside_temperature",
         "Temperature outside the house.",
         &["unit"]
     )
     .unwrap();
+    pub static ref WEATHER_OUTSIDE_TEMPERATURE_DELTA: GaugeVec = register_gauge_vec!(
+        "tado_weather_outside_temperature_delta_celsius",
+        "The change of the temperature outside the house since the previous weather observation, in celsius.",
+        &[]
+    )
+    .unwrap();
     pub static ref SENSOR_WINDOW_OPENED: GaugeVec = register_gauge_vec!(
         "tado_sensor_window_opened",
         "1 if the sensor detected a window is open, 0 otherwise.",
         &["zone", "type"]
     )
     .unwrap();
+    pub static ref ZONE_OPEN_WINDOW_AGE: GaugeVec = register_gauge_vec!(
+        "tado_zone_open_window_detected_age_seconds",
+        "The number of seconds since an open window was detected in a specific zone.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_ACTIVITY_DATA_AGE: GaugeVec = register_gauge_vec!(
+        "tado_zone_activity_data_age_seconds",
+        "The number of seconds since the heating or AC activity of a specific zone was last reported.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_AWAY_COMPLIANCE: GaugeVec = register_gauge_vec!(
+        "tado_zone_away_compliance",
+        "1 if a specific zone applies its away setting while the home is away, 0 otherwise.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_OVERLAY_DESYNC: GaugeVec = register_gauge_vec!(
+        "tado_zone_overlay_desync",
+        "1 if the overlay of a specific zone is still reported although it expired, 0 otherwise.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_BOOST_END: GaugeVec = register_gauge_vec!(
+        "tado_zone_boost_end_timestamp_seconds",
+        "The time the overlay of a specific zone ends at, e.g. at the end of a boost timer, in seconds since the epoch.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_UNSUPPORTED: GaugeVec = register_gauge_vec!(
+        "tado_zone_unsupported",
+        "1 for each zone whose state the exporter can't represent, along with the type of its setting.",
+        &["zone", "type"]
+    )
+    .unwrap();
+    pub static ref ZONE_HUMIDITY_OUT_OF_RANGE: GaugeVec = register_gauge_vec!(
+        "tado_zone_humidity_out_of_range",
+        "1 if the humidity of a specific zone is outside of the configured comfort band, 0 otherwise.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_VALVE_OPEN: GaugeVec = register_gauge_vec!(
+        "tado_zone_valve_open",
+        "1 if the valves of a specific zone are open, as derived from its heating power, 0 otherwise.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_TIME_TO_TARGET: GaugeVec = register_gauge_vec!(
+        "tado_zone_time_to_target_seconds",
+        "The estimated number of seconds until a specific zone warms up to its setpoint, at its current warming rate.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_NEXT_SCHEDULE_CHANGE: GaugeVec = register_gauge_vec!(
+        "tado_zone_next_schedule_change_seconds",
+        "The number of seconds until the schedule of a specific zone changes its setting.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_NEXT_SCHEDULE_TARGET: GaugeVec = register_gauge_vec!(
+        "tado_zone_next_schedule_change_target_celsius",
+        "The temperature the next schedule change of a specific zone sets, in celsius degrees.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_VALUE_STALE: GaugeVec = register_gauge_vec!(
+        "tado_zone_value_stale",
+        "1 if the values of a specific zone are held over from a previous scrape, 0 otherwise.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_POWER_ON: GaugeVec = register_gauge_vec!(
+        "tado_zone_power_on",
+        "1 if a specific zone is switched on, 0 if it is off.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_STALE: GaugeVec = register_gauge_vec!(
+        "tado_zone_stale",
+        "1 if the zone's sensor readings did not change since the previous scrape, 0 otherwise.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref SCHEDULED_TARGET: GaugeVec = register_gauge_vec!(
+        "tado_zone_scheduled_target_celsius",
+        "The temperature the schedule of a specific zone currently sets, in celsius degrees.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_MANUAL_CHANGES: IntCounterVec = register_int_counter_vec!(
+        "tado_zone_manual_changes_total",
+        "The number of manual changes observed in a specific zone since the exporter started.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref HOME_AUTO_ACTIONS: IntCounterVec = register_int_counter_vec!(
+        "tado_home_auto_actions_total",
+        "The number of automated actions observed in the home since the exporter started.",
+        &["type"]
+    )
+    .unwrap();
+    pub static ref DEVICE_ZONE_INFO: GaugeVec = register_gauge_vec!(
+        "tado_device_zone_info",
+        "1 for the zone served by a specific device, \"home\" for devices serving the whole home.",
+        &["serial", "zone"]
+    )
+    .unwrap();
+    pub static ref BRIDGE_CONNECTED: GaugeVec = register_gauge_vec!(
+        "tado_bridge_connected",
+        "1 if a specific internet bridge is connected to tado°, 0 otherwise.",
+        &["serial"]
+    )
+    .unwrap();
+    pub static ref DEVICE_FAULT: GaugeVec = register_gauge_vec!(
+        "tado_device_fault",
+        "1 for the fault code reported by a specific device, e.g. MOUNTING_ERROR.",
+        &["serial", "code"]
+    )
+    .unwrap();
+    pub static ref ZONE_INFO: GaugeVec = register_gauge_vec!(
+        "tado_zone_info",
+        "1 for the inventory metadata of a specific zone.",
+        &["zone", "name", "zone_id", "type", "date_created"]
+    )
+    .unwrap();
+    pub static ref ZONE_HEATING_CIRCUIT: GaugeVec = register_gauge_vec!(
+        "tado_zone_heating_circuit",
+        "1 for the heating circuit driving a specific zone.",
+        &["zone", "circuit"]
+    )
+    .unwrap();
+    pub static ref HOME_GEOFENCING_OVERRIDDEN: GaugeVec = register_gauge_vec!(
+        "tado_home_geofencing_overridden",
+        "1 if the presence of the home is locked manually rather than following geofencing, 0 otherwise.",
+        &[]
+    )
+    .unwrap();
+    pub static ref MOBILE_DEVICE_PUSH_ENABLED: GaugeVec = register_gauge_vec!(
+        "tado_mobile_device_push_enabled",
+        "1 if the presence-based push notifications of a specific mobile device are enabled, 0 otherwise.",
+        &["name"]
+    )
+    .unwrap();
+    pub static ref ZONE_GROUP_INFO: GaugeVec = register_gauge_vec!(
+        "tado_zone_group_info",
+        "1 for the group a specific zone belongs to.",
+        &["zone", "group"]
+    )
+    .unwrap();
+    pub static ref HOME_TIMEZONE_INFO: GaugeVec = register_gauge_vec!(
+        "tado_home_timezone_info",
+        "1 for the time zone configured for the home.",
+        &["tz"]
+    )
+    .unwrap();
+    pub static ref HOME_LOCATION_INFO: GaugeVec = register_gauge_vec!(
+        "tado_home_location_info",
+        "1 for the coordinates configured for the home.",
+        &["lat", "lon"]
+    )
+    .unwrap();
+    pub static ref HOME_TEMPERATURE_UNIT: GaugeVec = register_gauge_vec!(
+        "tado_home_temperature_unit",
+        "1 for the temperature unit preferred for the home in the tado° app.",
+        &["unit"]
+    )
+    .unwrap();
+    pub static ref ZONE_HEATING_SECONDS: CounterVec = register_counter_vec!(
+        "tado_zone_heating_seconds_total",
+        "The number of seconds a specific zone was observed heating.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ZONE_REJECTED_READINGS: IntCounterVec = register_int_counter_vec!(
+        "tado_zone_rejected_readings_total",
+        "The number of implausible temperature readings dropped in a specific zone.",
+        &["zone"]
+    )
+    .unwrap();
+    pub static ref ACTIVE_CREDENTIAL: GaugeVec = register_gauge_vec!(
+        "tado_active_credential",
+        "1 for the credentials the exporter authenticates with, primary or secondary, 0 for the others.",
+        &["which"]
+    )
+    .unwrap();
+    pub static ref CIRCUIT_BREAKER_STATE: GaugeVec = register_gauge_vec!(
+        "tado_circuit_breaker_state",
+        "1 for the current state of the circuit breaker guarding scrapes, 0 for the others.",
+        &["state"]
+    )
+    .unwrap();
+    pub static ref API_CLOCK_SKEW: GaugeVec = register_gauge_vec!(
+        "tado_api_clock_skew_seconds",
+        "The difference between the tado° API clock and the local one, positive when the local clock is behind.",
+        &[]
+    )
+    .unwrap();
+    pub static ref CONSECUTIVE_SCRAPE_FAILURES: GaugeVec = register_gauge_vec!(
+        "tado_consecutive_scrape_failures",
+        "The number of scrapes failed in a row, 0 after a successful scrape.",
+        &[]
+    )
+    .unwrap();
+    pub static ref AUTHENTICATED: GaugeVec = register_gauge_vec!(
+        "tado_authenticated",
+        "1 while the exporter holds a valid access token for the tado° API.",
+        &[]
+    )
+    .unwrap();
+    pub static ref API_MAINTENANCE: GaugeVec = register_gauge_vec!(
+        "tado_api_maintenance",
+        "1 while the tado° API is under maintenance and requests are paused.",
+        &[]
+    )
+    .unwrap();
+    pub static ref API_REQUEST_RETRIES: HistogramVec = register_histogram_vec!(
+        "tado_api_request_retries",
+        "The number of times requests to a specific tado° API endpoint were retried.",
+        &["endpoint"],
+        vec![0.0, 1.0, 2.0, 3.0, 5.0, 10.0]
+    )
+    .unwrap();
+    pub static ref WEATHER_DISABLED: GaugeVec = register_gauge_vec!(
+        "tado_weather_disabled",
+        "1 while the weather is no longer retrieved after failing repeatedly.",
+        &[]
+    )
+    .unwrap();
+    pub static ref HTTP_REQUESTS_INFLIGHT: GaugeVec = register_gauge_vec!(
+        "tado_http_requests_inflight",
+        "The number of requests to the tado° API waiting for a response.",
+        &[]
+    )
+    .unwrap();
+    pub static ref SCRAPE_RETRIES: IntCounterVec = register_int_counter_vec!(
+        "tado_scrape_retries_total",
+        "The number of tado° API requests retried.",
+        &[]
+    )
+    .unwrap();
+    pub static ref SCRAPE_DEADLINE_EXCEEDED: IntCounterVec = register_int_counter_vec!(
+        "tado_scrape_deadline_exceeded_total",
+        "The number of scrapes failed because they did not complete within the deadline.",
+        &[]
+    )
+    .unwrap();
 }
 
-pub fn set_zones(zones: Vec<ZoneStateResponse>) {
+pub fn set_build_info() {
+    BUILD_INFO
+        .with_label_values(&[
+            env!("CARGO_PKG_VERSION"),
+            env!("GIT_SHA"),
+            env!("RUSTC_VERSION"),
+        ])
+        .set(1.0);
+}
+
+pub fn set_zones(zones: &[ZoneStateResponse]) {
     for zone in zones {
-        let device_type: String = zone.state_response.setting.deviceType;
+        let device_type: &String = &zone.state_response.setting.deviceType;
+
+        // Zones switched off report no target and no heating: their gauges
+        // are removed, and the power gauge tells them apart from zones
+        // missing data.
+        let powered_on = zone.state_response.setting.is_powered_on();
+        ZONE_POWER_ON
+            .with_label_values(&[zone.name.as_str()])
+            .set(if powered_on { 1.0 } else { 0.0 });
+        debug!(
+            "-> {} ({}) -> power on: {}",
+            zone.name,
+            device_type.as_str(),
+            powered_on
+        );
 
         // The setting temperature may be null in the API response, if the
         // zone's heating mode is turned off. If the temperature setting is
-        // absent, from the API response we'll simply not set its gauge values.
-        if let Some(setting_temperature) = zone.state_response.setting.temperature {
+        // absent from the API response, we'll remove its gauge values rather
+        // than keep reporting the last temperature that was set.
+        let setting_temperature = zone
+            .state_response
+            .setting
+            .temperature
+            .as_ref()
+            .filter(|_| powered_on);
+        if let Some(setting_temperature) = setting_temperature {
             // setting temperature
             let value: f64 = setting_temperature.celsius;
             SETTING_TEMPERATURE
                 .with_label_values(&[zone.name.as_str(), device_type.as_str(), "celsius"])
                 .set(value);
-            info!(
+            debug!(
                 "-> {} ({}) -> setting temperature (celsius): {}",
                 zone.name,
                 device_type.as_str(),
@@ -82,19 +461,26 @@ pub fn set_zones(zones: Vec<ZoneStateResponse>) {
             SETTING_TEMPERATURE
                 .with_label_values(&[zone.name.as_str(), device_type.as_str(), "fahrenheit"])
                 .set(value);
-            info!(
+            debug!(
                 "-> {} ({}) -> setting temperature (fahrenheit): {}",
                 zone.name,
                 device_type.as_str(),
                 value
             );
         } else {
-            info!(
+            for unit in ["celsius", "fahrenheit"] {
+                let _ = SETTING_TEMPERATURE.remove_label_values(&[
+                    zone.name.as_str(),
+                    device_type.as_str(),
+                    unit,
+                ]);
+            }
+            debug!(
                 "-> {} ({}) -> setting temperature (celsius): Off",
                 zone.name,
                 device_type.as_str()
             );
-            info!(
+            debug!(
                 "-> {} ({}) -> setting temperature (fahrenheit): Off",
                 zone.name,
                 device_type.as_str()
@@ -103,7 +489,7 @@ pub fn set_zones(zones: Vec<ZoneStateResponse>) {
 
         // If openWindowDetected is not None, this means that a window is open.
         if zone.state_response.openWindow.is_some() {
-            info!(
+            debug!(
                 "-> {} ({}) -> window opened: {}",
                 zone.name,
                 device_type.as_str(),
@@ -113,7 +499,7 @@ pub fn set_zones(zones: Vec<ZoneStateResponse>) {
                 .with_label_values(&[zone.name.as_str(), device_type.as_str()])
                 .set(1.0);
         } else {
-            info!(
+            debug!(
                 "-> {} ({}) -> window opened: {}",
                 zone.name,
                 device_type.as_str(),
@@ -124,14 +510,23 @@ pub fn set_zones(zones: Vec<ZoneStateResponse>) {
                 .set(0.0);
         }
 
+        // Sensor and activity data points are only reported by the zones
+        // equipped for them, and may go missing (e.g. while a device is
+        // offline). Absent values are never defaulted: their gauges are
+        // removed, so graphs show a gap rather than a misleading value.
+
+        let sensor_data_points = zone.state_response.sensorDataPoints.as_ref();
+
         // sensor temperature
-        if let Some(inside_temperature) = zone.state_response.sensorDataPoints.insideTemperature {
+        if let Some(inside_temperature) =
+            sensor_data_points.and_then(|sensor| sensor.insideTemperature.as_ref())
+        {
             // celsius
             let value: f64 = inside_temperature.celsius;
             SENSOR_TEMPERATURE
                 .with_label_values(&[zone.name.as_str(), device_type.as_str(), "celsius"])
                 .set(value);
-            info!(
+            debug!(
                 "-> {} ({}) -> sensor temperature (celsius): {}",
                 zone.name,
                 device_type.as_str(),
@@ -143,178 +538,1644 @@ pub fn set_zones(zones: Vec<ZoneStateResponse>) {
             SENSOR_TEMPERATURE
                 .with_label_values(&[zone.name.as_str(), device_type.as_str(), "fahrenheit"])
                 .set(value);
-            info!(
+            debug!(
                 "-> {} ({}) -> sensor temperature (fahrenheit): {}",
                 zone.name,
                 device_type.as_str(),
                 value
             );
+        } else {
+            for unit in ["celsius", "fahrenheit"] {
+                let _ = SENSOR_TEMPERATURE.remove_label_values(&[
+                    zone.name.as_str(),
+                    device_type.as_str(),
+                    unit,
+                ]);
+            }
         }
 
         // sensor humidity
-        if let Some(humidity) = zone.state_response.sensorDataPoints.humidity {
+        if let Some(humidity) = sensor_data_points.and_then(|sensor| sensor.humidity.as_ref()) {
             let value: f64 = humidity.percentage;
             SENSOR_HUMIDITY_PERCENTAGE
                 .with_label_values(&[zone.name.as_str(), device_type.as_str()])
                 .set(value);
-            info!(
+            debug!(
                 "-> {} ({}) -> sensor humidity: {}%",
                 zone.name,
                 device_type.as_str(),
                 value
             );
+        } else {
+            let _ = SENSOR_HUMIDITY_PERCENTAGE
+                .remove_label_values(&[zone.name.as_str(), device_type.as_str()]);
         }
 
         // heating power
-        if let Some(heating_power) = zone.state_response.activityDataPoints.heatingPower {
+        let heating_power = zone
+            .state_response
+            .activityDataPoints
+            .heatingPower
+            .as_ref()
+            .filter(|_| powered_on);
+        if let Some(heating_power) = heating_power {
             let value: f64 = heating_power.percentage;
             ACTIVITY_HEATING_POWER
                 .with_label_values(&[zone.name.as_str(), device_type.as_str()])
                 .set(value);
-            info!(
+            debug!(
                 "-> {} ({}) -> heating power: {}%",
                 zone.name,
                 device_type.as_str(),
                 value
             );
+        } else {
+            let _ = ACTIVITY_HEATING_POWER
+                .remove_label_values(&[zone.name.as_str(), device_type.as_str()]);
         }
 
         // ac power
-        if let Some(ac_power) = zone.state_response.activityDataPoints.acPower {
-            let value: f64 = match ac_power.value.as_str() {
-                "ON" => 1.0,
-                "OFF" => 0.0,
-                _ => 0.0,
-            };
+        let ac_power_value = match &zone.state_response.activityDataPoints.acPower {
+            Some(ac_power) => match ac_power.value.as_str() {
+                "ON" => Some(1.0),
+                "OFF" => Some(0.0),
+                value => {
+                    warn!(
+                        "-> {} ({}) -> unknown ac power value: {}",
+                        zone.name,
+                        device_type.as_str(),
+                        value
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
 
+        if let Some(value) = ac_power_value {
             ACTIVITY_AC_POWER
                 .with_label_values(&[zone.name.as_str(), device_type.as_str()])
                 .set(value);
-            info!(
+            debug!(
                 "-> {} ({}) -> ac power: {}",
                 zone.name,
                 device_type.as_str(),
                 value
             );
+        } else {
+            let _ =
+                ACTIVITY_AC_POWER.remove_label_values(&[zone.name.as_str(), device_type.as_str()]);
+        }
+
+        // scheduled target, omitted for zones without a schedule or whose
+        // active block turns the zone off
+        let scheduled_temperature = zone
+            .scheduled_setting
+            .as_ref()
+            .and_then(|setting| setting.temperature.as_ref());
+        if let Some(temperature) = scheduled_temperature {
+            let value: f64 = temperature.celsius;
+            SCHEDULED_TARGET
+                .with_label_values(&[zone.name.as_str()])
+                .set(value);
+            debug!(
+                "-> {} ({}) -> scheduled target (celsius): {}",
+                zone.name,
+                device_type.as_str(),
+                value
+            );
+        } else {
+            let _ = SCHEDULED_TARGET.remove_label_values(&[zone.name.as_str()]);
+        }
+
+        // away compliance, omitted unless the home is away and the away
+        // setting of the zone is known
+        let away = zone.state_response.tadoMode.as_deref() == Some("AWAY");
+        match zone.away_setting.as_ref().filter(|_| away) {
+            Some(away_setting) => {
+                let compliant = zone.state_response.setting.same_target(away_setting);
+                ZONE_AWAY_COMPLIANCE
+                    .with_label_values(&[zone.name.as_str()])
+                    .set(if compliant { 1.0 } else { 0.0 });
+                debug!("-> {} -> away compliance: {}", zone.name, compliant);
+            }
+            None => {
+                let _ = ZONE_AWAY_COMPLIANCE.remove_label_values(&[zone.name.as_str()]);
+            }
         }
     }
 }
 
-pub fn set_weather(weather_response: Option<WeatherApiResponse>) {
-    if let Some(weather) = weather_response {
-        // setting solar intensity
-        let solar_intensity_percentage = weather.solarIntensity.percentage;
+pub fn set_mobile_devices(mobile_devices: &[MobileDeviceApiResponse]) {
+    // mobile devices may be renamed or removed, drop previous settings
+    MOBILE_DEVICE_PUSH_ENABLED.reset();
 
-        WEATHER_SOLAR_INTENSITY
-            .with_label_values(&[])
-            .set(weather.solarIntensity.percentage);
-        info!(
-            "-> setting solar intensity (percentage): {}",
-            solar_intensity_percentage
-        );
+    for mobile_device in mobile_devices {
+        if let Some(push_enabled) = mobile_device.push_enabled() {
+            debug!(
+                "-> mobile device {} -> push enabled: {}",
+                mobile_device.name, push_enabled
+            );
+            MOBILE_DEVICE_PUSH_ENABLED
+                .with_label_values(&[mobile_device.name.as_str()])
+                .set(if push_enabled { 1.0 } else { 0.0 });
+        }
+    }
+}
 
-        // setting outside temperature
-        let outside_temperature_celsius = weather.outsideTemperature.celsius;
-        let outside_temperature_fahrenheit = weather.outsideTemperature.fahrenheit;
+pub fn set_home_state(
+    home_state: Option<&HomeStateApiResponse>,
+    mobile_devices: &[MobileDeviceApiResponse],
+) {
+    let Some(home_state) = home_state else {
+        HOME_GEOFENCING_OVERRIDDEN.reset();
+        return;
+    };
 
-        WEATHER_OUTSIDE_TEMPERATURE
-            .with_label_values(&["celsius"])
-            .set(outside_temperature_celsius);
-        info!(
-            "-> setting outside temperature (celsius): {}",
-            outside_temperature_celsius
-        );
+    // a locked presence only overrides geofencing if a mobile device tracks
+    // its location, which is assumed when mobile devices aren't collected
+    let geofencing = mobile_devices.is_empty()
+        || mobile_devices
+            .iter()
+            .any(|mobile_device| mobile_device.settings.geoTrackingEnabled);
+    let overridden = home_state.presenceLocked && geofencing;
 
-        WEATHER_OUTSIDE_TEMPERATURE
-            .with_label_values(&["fahrenheit"])
-            .set(outside_temperature_fahrenheit);
-        info!(
-            "-> setting outside temperature (fahrenheit): {}",
-            outside_temperature_fahrenheit
-        );
-    }
+    debug!("-> home -> geofencing overridden: {}", overridden);
+    HOME_GEOFENCING_OVERRIDDEN
+        .with_label_values(&[])
+        .set(if overridden { 1.0 } else { 0.0 });
 }
 
-pub async fn renderer(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
-    let metrics = prometheus::gather();
-    let mut buffer = vec![];
+pub fn set_zone_info(zones: &[ZonesApiResponse], zone_label: ZoneLabel) {
+    // zones may be renamed or removed, drop previous metadata
+    ZONE_INFO.reset();
+    ZONE_HEATING_CIRCUIT.reset();
 
-    let encoder = TextEncoder::new();
-    encoder.encode(&metrics, &mut buffer).unwrap();
+    for zone in zones {
+        let label = zone_label.of(zone);
 
-    let response = Response::builder()
-        .status(200)
-        .header(CONTENT_TYPE, encoder.format_type())
-        .body(Body::from(buffer))
-        .unwrap();
+        ZONE_INFO
+            .with_label_values(&[
+                label.as_str(),
+                zone.name.as_str(),
+                zone.id.to_string().as_str(),
+                zone.zoneType.as_str(),
+                zone.dateCreated.as_str(),
+            ])
+            .set(1.0);
 
-    Ok(response)
+        if let Some(circuit) = zone.heatingCircuit {
+            debug!("-> {} -> heating circuit: {}", zone.name, circuit);
+            ZONE_HEATING_CIRCUIT
+                .with_label_values(&[label.as_str(), circuit.to_string().as_str()])
+                .set(1.0);
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::tado::model::{
-        WeatherOutsideTemperatureApiResponse, WeatherSolarIntensityApiResponse,
-    };
+pub fn set_zone_groups(zones: &[ZoneStateResponse], groups: &HashMap<String, String>) {
+    // only report the groups of the zones currently in the home
+    ZONE_GROUP_INFO.reset();
 
-    use super::*;
+    for zone in zones {
+        if let Some(group) = groups.get(&zone.name) {
+            debug!("-> {} -> group: {}", zone.name, group);
+            ZONE_GROUP_INFO
+                .with_label_values(&[zone.name.as_str(), group.as_str()])
+                .set(1.0);
+        }
+    }
+}
 
-    #[test]
-    fn test_set_weather_some() {
-        /*
-        GIVEN a weather response
-        WHEN set_weather is called
-        THEN the metrics are set
-        */
+pub fn set_home_timezone(tz: &str) {
+    debug!("-> home -> time zone: {}", tz);
+    HOME_TIMEZONE_INFO.reset();
+    HOME_TIMEZONE_INFO.with_label_values(&[tz]).set(1.0);
+}
 
-        // GIVEN
-        let weather_response = WeatherApiResponse {
-            solarIntensity: WeatherSolarIntensityApiResponse { percentage: 100.0 },
-            outsideTemperature: WeatherOutsideTemperatureApiResponse {
-                celsius: 20.0,
-                fahrenheit: 68.0,
-            },
-        };
+pub fn set_home_location(geolocation: Option<GeolocationApiResponse>) {
+    HOME_LOCATION_INFO.reset();
 
-        // WHEN
-        set_weather(Some(weather_response));
+    if let Some(geolocation) = geolocation {
+        debug!(
+            "-> home -> location: {}, {}",
+            geolocation.latitude, geolocation.longitude
+        );
+        HOME_LOCATION_INFO
+            .with_label_values(&[
+                &geolocation.latitude.to_string(),
+                &geolocation.longitude.to_string(),
+            ])
+            .set(1.0);
+    }
+}
 
-        // THEN
-        // Check metrics
-        let metrics = prometheus::gather();
+pub fn set_home_temperature_unit(unit: &str) {
+    let unit = unit.to_lowercase();
+    debug!("-> home -> temperature unit: {}", unit);
+    HOME_TEMPERATURE_UNIT.reset();
+    HOME_TEMPERATURE_UNIT.with_label_values(&[&unit]).set(1.0);
+}
 
-        assert_eq!(metrics.len(), 2);
-        assert_eq!(metrics[0].get_name(), "weather_outside_temperature");
-        assert_eq!(metrics[1].get_name(), "weather_solar_intensity");
+pub fn set_devices(devices: &[DeviceZoneResponse]) {
+    // devices may be moved to another zone or removed, drop previous mappings
+    DEVICE_ZONE_INFO.reset();
+    BRIDGE_CONNECTED.reset();
+    DEVICE_FAULT.reset();
 
-        // Check outside temperature metric
-        let outside_temperature_metric = metrics[0].get_metric();
+    for device in devices {
+        let zone = device.zone.as_deref().unwrap_or("home");
+        debug!("-> device {} -> zone: {}", device.serial, zone);
+        DEVICE_ZONE_INFO
+            .with_label_values(&[device.serial.as_str(), zone])
+            .set(1.0);
 
-        assert_eq!(outside_temperature_metric.len(), 2);
+        // when the bridge is offline, so is every other device
+        if let Some(connected) = device.connected.filter(|_| device.is_bridge()) {
+            debug!("-> bridge {} -> connected: {}", device.serial, connected);
+            BRIDGE_CONNECTED
+                .with_label_values(&[device.serial.as_str()])
+                .set(if connected { 1.0 } else { 0.0 });
+        }
 
-        let outside_temp_celsius = &outside_temperature_metric[0];
-        let outside_temp_fahrenheit = &outside_temperature_metric[1];
+        if let Some(fault) = &device.fault {
+            warn!("-> device {} -> fault: {}", device.serial, fault);
+            DEVICE_FAULT
+                .with_label_values(&[device.serial.as_str(), fault.as_str()])
+                .set(1.0);
+        }
+    }
+}
 
-        assert_eq!(outside_temp_celsius.get_label().len(), 1);
-        assert_eq!(outside_temp_celsius.get_label()[0].get_name(), "unit");
-        assert_eq!(outside_temp_celsius.get_label()[0].get_value(), "celsius");
-        assert_eq!(outside_temp_celsius.get_gauge().get_value(), 20.0);
-        assert_eq!(outside_temp_fahrenheit.get_label().len(), 1);
-        assert_eq!(outside_temp_fahrenheit.get_label()[0].get_name(), "unit");
-        assert_eq!(
-            outside_temp_fahrenheit.get_label()[0].get_value(),
-            "fahrenheit"
-        );
-        assert_eq!(outside_temp_fahrenheit.get_gauge().get_value(), 68.0);
+// Windows are only reported while open, so the age of a closed window is
+// removed rather than frozen.
+pub fn set_open_window_ages(zones: &[ZoneStateResponse], now: DateTime<Utc>) {
+    for zone in zones {
+        let detected_at = zone
+            .state_response
+            .openWindow
+            .as_ref()
+            .and_then(|open_window| DateTime::parse_from_rfc3339(&open_window.detectedTime).ok());
+
+        match detected_at {
+            Some(detected_at) => {
+                let age =
+                    (now - detected_at.with_timezone(&Utc)).num_milliseconds() as f64 / 1000.0;
+                ZONE_OPEN_WINDOW_AGE
+                    .with_label_values(&[zone.name.as_str()])
+                    .set(age.max(0.0));
+                debug!("-> {} -> open window age: {}s", zone.name, age);
+            }
+            None => {
+                let _ = ZONE_OPEN_WINDOW_AGE.remove_label_values(&[zone.name.as_str()]);
+            }
+        }
+    }
+}
 
-        // Check solar intensity metric
-        let solar_intensity_metric = metrics[1].get_metric();
+pub fn set_app_inside_temperatures(zones: &[ZoneStateResponse]) {
+    for zone in zones {
+        let inside_temperature = zone
+            .state_response
+            .sensorDataPoints
+            .as_ref()
+            .and_then(|sensor_data_points| sensor_data_points.insideTemperature.as_ref());
 
-        assert_eq!(solar_intensity_metric.len(), 1);
-        assert_eq!(solar_intensity_metric[0].get_gauge().get_value(), 100.0);
+        match inside_temperature {
+            Some(inside_temperature) => {
+                let celsius = round_like_app(inside_temperature.celsius);
+                ZONE_INSIDE_TEMPERATURE_APP
+                    .with_label_values(&[zone.name.as_str()])
+                    .set(celsius);
+                debug!(
+                    "-> {} -> inside temperature (app, celsius): {}",
+                    zone.name, celsius
+                );
+            }
+            None => {
+                let _ = ZONE_INSIDE_TEMPERATURE_APP.remove_label_values(&[zone.name.as_str()]);
+            }
+        }
     }
+}
 
-    #[test]
+// Round to the nearest 0.1 degree, halves up, as the tado° app does. The API
+// reports hundredths of a degree, which are rounded first so the binary
+// representation of e.g. 21.45 doesn't round it down.
+fn round_like_app(celsius: f64) -> f64 {
+    let hundredths = (celsius * 100.0).round() as i64;
+
+    (hundredths + 5).div_euclid(10) as f64 / 10.0
+}
+
+pub fn set_activity_data_ages(zones: &[ZoneStateResponse], now: DateTime<Utc>) {
+    for zone in zones {
+        match zone.state_response.activityDataPoints.reported_at() {
+            Some(reported_at) => {
+                let age = (now - reported_at).num_milliseconds() as f64 / 1000.0;
+                ZONE_ACTIVITY_DATA_AGE
+                    .with_label_values(&[zone.name.as_str()])
+                    .set(age.max(0.0));
+                debug!("-> {} -> activity data age: {}s", zone.name, age);
+            }
+            None => {
+                let _ = ZONE_ACTIVITY_DATA_AGE.remove_label_values(&[zone.name.as_str()]);
+            }
+        }
+    }
+}
+
+pub fn set_next_schedule_changes(zones: &[ZoneStateResponse], now: DateTime<Utc>) {
+    for zone in zones {
+        let next_change = zone.state_response.nextScheduleChange.as_ref();
+        let starts_at = next_change
+            .and_then(|next_change| DateTime::parse_from_rfc3339(&next_change.start).ok());
+
+        match starts_at {
+            Some(starts_at) => {
+                let seconds =
+                    (starts_at.with_timezone(&Utc) - now).num_milliseconds() as f64 / 1000.0;
+                ZONE_NEXT_SCHEDULE_CHANGE
+                    .with_label_values(&[zone.name.as_str()])
+                    .set(seconds.max(0.0));
+                debug!("-> {} -> next schedule change in: {}s", zone.name, seconds);
+            }
+            None => {
+                let _ = ZONE_NEXT_SCHEDULE_CHANGE.remove_label_values(&[zone.name.as_str()]);
+            }
+        }
+
+        // omitted when the next change turns the zone off
+        let target = next_change
+            .filter(|_| starts_at.is_some())
+            .and_then(|next_change| next_change.setting.as_ref())
+            .filter(|setting| setting.is_powered_on())
+            .and_then(|setting| setting.temperature.as_ref());
+        match target {
+            Some(temperature) => {
+                ZONE_NEXT_SCHEDULE_TARGET
+                    .with_label_values(&[zone.name.as_str()])
+                    .set(temperature.celsius);
+            }
+            None => {
+                let _ = ZONE_NEXT_SCHEDULE_TARGET.remove_label_values(&[zone.name.as_str()]);
+            }
+        }
+    }
+}
+
+pub fn set_overlay_desyncs(zones: &[ZoneStateResponse], now: DateTime<Utc>) {
+    for zone in zones {
+        let desync = zone
+            .state_response
+            .overlay
+            .as_ref()
+            .is_some_and(|overlay| overlay.is_expired(now));
+
+        if desync {
+            warn!("-> {} -> overlay expired but still reported", zone.name);
+        }
+        ZONE_OVERLAY_DESYNC
+            .with_label_values(&[zone.name.as_str()])
+            .set(if desync { 1.0 } else { 0.0 });
+    }
+}
+
+pub fn set_boost_ends(zones: &[ZoneStateResponse]) {
+    for zone in zones {
+        let ends_at = zone
+            .state_response
+            .overlay
+            .as_ref()
+            .and_then(|overlay| overlay.ends_at());
+
+        match ends_at {
+            Some(ends_at) => {
+                debug!("-> {} -> overlay ends at: {}", zone.name, ends_at);
+                ZONE_BOOST_END
+                    .with_label_values(&[zone.name.as_str()])
+                    .set(ends_at.timestamp_millis() as f64 / 1000.0);
+            }
+            None => {
+                let _ = ZONE_BOOST_END.remove_label_values(&[zone.name.as_str()]);
+            }
+        }
+    }
+}
+
+pub fn set_unsupported_zones(zones: &[UnsupportedZoneResponse]) {
+    ZONE_UNSUPPORTED.reset();
+    for zone in zones {
+        ZONE_UNSUPPORTED
+            .with_label_values(&[zone.name.as_str(), zone.setting_type.as_str()])
+            .set(1.0);
+    }
+}
+
+pub fn set_humidity_out_of_range(zones: &[ZoneStateResponse], min: f64, max: f64) {
+    for zone in zones {
+        let humidity = zone
+            .state_response
+            .sensorDataPoints
+            .as_ref()
+            .and_then(|sensor_data_points| sensor_data_points.humidity.as_ref());
+
+        match humidity {
+            Some(humidity) => {
+                let out_of_range = !(min..=max).contains(&humidity.percentage);
+                ZONE_HUMIDITY_OUT_OF_RANGE
+                    .with_label_values(&[zone.name.as_str()])
+                    .set(if out_of_range { 1.0 } else { 0.0 });
+            }
+            None => {
+                let _ = ZONE_HUMIDITY_OUT_OF_RANGE.remove_label_values(&[zone.name.as_str()]);
+            }
+        }
+    }
+}
+
+pub fn set_zone_time_to_target(zone: &str, seconds: Option<f64>) {
+    match seconds {
+        Some(seconds) => {
+            ZONE_TIME_TO_TARGET.with_label_values(&[zone]).set(seconds);
+            debug!("-> {} -> estimated time to target: {}s", zone, seconds);
+        }
+        None => {
+            let _ = ZONE_TIME_TO_TARGET.remove_label_values(&[zone]);
+        }
+    }
+}
+
+pub fn set_zone_valve_open(zone: &str, open: Option<bool>) {
+    match open {
+        Some(open) => {
+            ZONE_VALVE_OPEN
+                .with_label_values(&[zone])
+                .set(if open { 1.0 } else { 0.0 });
+            debug!("-> {} -> valve open: {}", zone, open);
+        }
+        None => {
+            let _ = ZONE_VALVE_OPEN.remove_label_values(&[zone]);
+        }
+    }
+}
+
+pub fn set_zone_value_stale(zone: &str, held: bool) {
+    if held {
+        warn!("-> {} -> holding the values of the previous scrape", zone);
+    }
+    ZONE_VALUE_STALE
+        .with_label_values(&[zone])
+        .set(if held { 1.0 } else { 0.0 });
+}
+
+pub fn set_zone_stale(zone: &str, stale: bool) {
+    debug!("-> {} -> stale: {}", zone, stale);
+    ZONE_STALE
+        .with_label_values(&[zone])
+        .set(if stale { 1.0 } else { 0.0 });
+}
+
+pub fn inc_zone_manual_changes(zone: &str) {
+    debug!("-> {} -> manual change observed", zone);
+    ZONE_MANUAL_CHANGES.with_label_values(&[zone]).inc();
+}
+
+pub fn inc_home_auto_actions(zone: &str, action_type: &str) {
+    debug!("-> {} -> automated action observed: {}", zone, action_type);
+    HOME_AUTO_ACTIONS.with_label_values(&[action_type]).inc();
+}
+
+pub fn inc_zone_heating_seconds(zone: &str, seconds: f64) {
+    ZONE_HEATING_SECONDS
+        .with_label_values(&[zone])
+        .inc_by(seconds);
+}
+
+// Drop an implausible temperature reading of a zone, rather than charting it.
+pub fn reject_zone_temperature(zone: &str, device_type: &str) {
+    for unit in ["celsius", "fahrenheit"] {
+        let _ = SENSOR_TEMPERATURE.remove_label_values(&[zone, device_type, unit]);
+    }
+    let _ = ZONE_INSIDE_TEMPERATURE_APP.remove_label_values(&[zone]);
+    ZONE_REJECTED_READINGS.with_label_values(&[zone]).inc();
+}
+
+pub fn inc_scrape_deadline_exceeded() {
+    SCRAPE_DEADLINE_EXCEEDED.with_label_values(&[]).inc();
+}
+
+pub fn set_api_clock_skew(seconds: f64) {
+    API_CLOCK_SKEW.with_label_values(&[]).set(seconds);
+}
+
+pub fn set_consecutive_scrape_failures(failures: u32) {
+    CONSECUTIVE_SCRAPE_FAILURES
+        .with_label_values(&[])
+        .set(failures as f64);
+}
+
+pub fn set_authenticated(authenticated: bool) {
+    AUTHENTICATED
+        .with_label_values(&[])
+        .set(if authenticated { 1.0 } else { 0.0 });
+}
+
+pub fn set_api_maintenance(maintenance: bool) {
+    API_MAINTENANCE
+        .with_label_values(&[])
+        .set(if maintenance { 1.0 } else { 0.0 });
+}
+
+pub fn observe_request_retries(endpoint: &str, retries: u32) {
+    API_REQUEST_RETRIES
+        .with_label_values(&[endpoint])
+        .observe(retries as f64);
+}
+
+pub fn set_weather_disabled(disabled: bool) {
+    WEATHER_DISABLED
+        .with_label_values(&[])
+        .set(if disabled { 1.0 } else { 0.0 });
+}
+
+pub fn set_http_requests_inflight(inflight: usize) {
+    HTTP_REQUESTS_INFLIGHT
+        .with_label_values(&[])
+        .set(inflight as f64);
+}
+
+pub fn inc_scrape_retries() {
+    SCRAPE_RETRIES.with_label_values(&[]).inc();
+}
+
+pub fn set_active_credential(current: &str) {
+    for which in ["primary", "secondary"] {
+        ACTIVE_CREDENTIAL
+            .with_label_values(&[which])
+            .set(if which == current { 1.0 } else { 0.0 });
+    }
+}
+
+pub fn set_circuit_breaker_state(current: &str) {
+    for state in ["closed", "open", "half_open"] {
+        CIRCUIT_BREAKER_STATE
+            .with_label_values(&[state])
+            .set(if state == current { 1.0 } else { 0.0 });
+    }
+}
+
+// Stop emitting the series of a zone removed from the home. Its counters are
+// removed separately, see `remove_zone_counters`.
+pub fn remove_zone(zone: &str, device_type: &str) {
+    remove_zone_sensor(zone, device_type);
+    for unit in ["celsius", "fahrenheit"] {
+        let _ = SETTING_TEMPERATURE.remove_label_values(&[zone, device_type, unit]);
+    }
+    for gauge in [
+        &*ACTIVITY_HEATING_POWER,
+        &*ACTIVITY_AC_POWER,
+        &*SENSOR_WINDOW_OPENED,
+    ] {
+        let _ = gauge.remove_label_values(&[zone, device_type]);
+    }
+    for gauge in [
+        &*ZONE_OPEN_WINDOW_AGE,
+        &*ZONE_ACTIVITY_DATA_AGE,
+        &*ZONE_OVERLAY_DESYNC,
+        &*ZONE_BOOST_END,
+        &*ZONE_HUMIDITY_OUT_OF_RANGE,
+        &*ZONE_TIME_TO_TARGET,
+        &*ZONE_VALVE_OPEN,
+        &*ZONE_AWAY_COMPLIANCE,
+        &*ZONE_NEXT_SCHEDULE_CHANGE,
+        &*ZONE_NEXT_SCHEDULE_TARGET,
+        &*ZONE_VALUE_STALE,
+        &*ZONE_POWER_ON,
+        &*ZONE_STALE,
+        &*SCHEDULED_TARGET,
+    ] {
+        let _ = gauge.remove_label_values(&[zone]);
+    }
+}
+
+pub fn remove_zone_counters(zone: &str) {
+    let _ = ZONE_MANUAL_CHANGES.remove_label_values(&[zone]);
+    let _ = ZONE_REJECTED_READINGS.remove_label_values(&[zone]);
+    let _ = ZONE_HEATING_SECONDS.remove_label_values(&[zone]);
+}
+
+// Stop emitting the sensor series of a zone, so its last value is not
+// reported again until new readings are available.
+pub fn remove_zone_sensor(zone: &str, device_type: &str) {
+    for unit in ["celsius", "fahrenheit"] {
+        let _ = SENSOR_TEMPERATURE.remove_label_values(&[zone, device_type, unit]);
+    }
+    let _ = SENSOR_HUMIDITY_PERCENTAGE.remove_label_values(&[zone, device_type]);
+    let _ = ZONE_INSIDE_TEMPERATURE_APP.remove_label_values(&[zone]);
+}
+
+pub fn set_weather(weather_response: Option<&WeatherApiResponse>) {
+    if let Some(weather) = weather_response {
+        // setting solar intensity
+        let solar_intensity_percentage = weather.solarIntensity.percentage;
+
+        WEATHER_SOLAR_INTENSITY
+            .with_label_values(&[])
+            .set(weather.solarIntensity.percentage);
+        debug!(
+            "-> setting solar intensity (percentage): {}",
+            solar_intensity_percentage
+        );
+
+        // the same value as a ratio, to compose with other 0-1 signals
+        let solar_intensity_ratio = solar_intensity_percentage / 100.0;
+
+        WEATHER_SOLAR_INTENSITY_RATIO
+            .with_label_values(&[])
+            .set(solar_intensity_ratio);
+        debug!(
+            "-> setting solar intensity (ratio): {}",
+            solar_intensity_ratio
+        );
+
+        // setting outside temperature
+        let outside_temperature_celsius = weather.outsideTemperature.celsius;
+        let outside_temperature_fahrenheit = weather.outsideTemperature.fahrenheit;
+
+        WEATHER_OUTSIDE_TEMPERATURE
+            .with_label_values(&["celsius"])
+            .set(outside_temperature_celsius);
+        debug!(
+            "-> setting outside temperature (celsius): {}",
+            outside_temperature_celsius
+        );
+
+        WEATHER_OUTSIDE_TEMPERATURE
+            .with_label_values(&["fahrenheit"])
+            .set(outside_temperature_fahrenheit);
+        debug!(
+            "-> setting outside temperature (fahrenheit): {}",
+            outside_temperature_fahrenheit
+        );
+    }
+
+    // only the current weather state is reported, drop the previous one
+    WEATHER_STATE.reset();
+
+    if let Some(weather_state) = weather_response.and_then(|w| w.weatherState.as_ref()) {
+        WEATHER_STATE
+            .with_label_values(&[
+                weather_state.dataType.as_str(),
+                weather_state.value.as_str(),
+            ])
+            .set(1.0);
+        debug!("-> setting weather state: {}", weather_state.value);
+    }
+}
+
+pub fn set_weather_outside_temperature_delta(delta_celsius: f64) {
+    WEATHER_OUTSIDE_TEMPERATURE_DELTA
+        .with_label_values(&[])
+        .set(delta_celsius);
+    debug!(
+        "-> setting outside temperature delta (celsius): {}",
+        delta_celsius
+    );
+}
+
+// Omitted when the location of the house is unknown.
+pub fn set_weather_daylight(daylight: Option<bool>) {
+    match daylight {
+        Some(daylight) => {
+            WEATHER_IS_DAYLIGHT
+                .with_label_values(&[])
+                .set(if daylight { 1.0 } else { 0.0 });
+            debug!("-> setting daylight: {}", daylight);
+        }
+        None => WEATHER_IS_DAYLIGHT.reset(),
+    }
+}
+
+/// Replace the `tado` prefix of the metrics names, or drop it if `namespace`
+/// is empty. The weather metrics, historically unprefixed, keep their names.
+pub fn set_namespace(namespace: &str) -> Result<(), String> {
+    let mut chars = namespace.chars();
+    let valid = match chars.next() {
+        None => true,
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_' || first == ':')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+        }
+    };
+
+    if !valid {
+        return Err(format!("invalid metric namespace: {}", namespace));
+    }
+
+    *NAMESPACE.write().unwrap() = namespace.to_string();
+
+    Ok(())
+}
+
+/// Only render the metrics allowed by `filter`. Their values are still
+/// collected, e.g. for the state endpoint.
+pub fn set_metric_filter(filter: MetricFilter) {
+    *METRIC_FILTER.write().unwrap() = filter;
+}
+
+/// Render the registered metrics in the Prometheus text format.
+pub fn render() -> Vec<u8> {
+    let mut metrics = prometheus::gather();
+    let mut buffer = vec![];
+
+    let filter = METRIC_FILTER.read().unwrap();
+    metrics.retain(|family| filter.allows(family.get_name()));
+
+    let namespace = NAMESPACE.read().unwrap();
+    if namespace.as_str() != DEFAULT_NAMESPACE {
+        let prefix = format!("{}_", DEFAULT_NAMESPACE);
+        for family in metrics.iter_mut() {
+            if let Some(name) = family.get_name().strip_prefix(&prefix) {
+                let name = if namespace.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}_{}", namespace, name)
+                };
+                family.set_name(name);
+            }
+        }
+    }
+
+    let encoder = TextEncoder::new();
+    encoder.encode(&metrics, &mut buffer).unwrap();
+
+    buffer
+}
+
+pub async fn renderer(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, TextEncoder::new().format_type())
+        .body(Body::from(render()))
+        .unwrap();
+
+    Ok(response)
+}
+
+/// Serialize tests that touch the global registry and reset every gauge, so
+/// each test starts from an empty registry regardless of execution order.
+#[cfg(test)]
+pub fn lock_registry() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    set_namespace(DEFAULT_NAMESPACE).unwrap();
+    set_metric_filter(MetricFilter::default());
+
+    ACTIVITY_HEATING_POWER.reset();
+    ACTIVITY_AC_POWER.reset();
+    SETTING_TEMPERATURE.reset();
+    SENSOR_TEMPERATURE.reset();
+    ZONE_INSIDE_TEMPERATURE_APP.reset();
+    SENSOR_HUMIDITY_PERCENTAGE.reset();
+    WEATHER_SOLAR_INTENSITY.reset();
+    WEATHER_SOLAR_INTENSITY_RATIO.reset();
+    WEATHER_OUTSIDE_TEMPERATURE.reset();
+    WEATHER_OUTSIDE_TEMPERATURE_DELTA.reset();
+    WEATHER_STATE.reset();
+    WEATHER_IS_DAYLIGHT.reset();
+    SENSOR_WINDOW_OPENED.reset();
+    ZONE_STALE.reset();
+    ZONE_POWER_ON.reset();
+    ZONE_OPEN_WINDOW_AGE.reset();
+    ZONE_ACTIVITY_DATA_AGE.reset();
+    ZONE_OVERLAY_DESYNC.reset();
+    ZONE_BOOST_END.reset();
+    ZONE_HUMIDITY_OUT_OF_RANGE.reset();
+    ZONE_TIME_TO_TARGET.reset();
+    ZONE_VALVE_OPEN.reset();
+    ZONE_UNSUPPORTED.reset();
+    ZONE_AWAY_COMPLIANCE.reset();
+    ZONE_NEXT_SCHEDULE_CHANGE.reset();
+    ZONE_NEXT_SCHEDULE_TARGET.reset();
+    ZONE_VALUE_STALE.reset();
+    SCHEDULED_TARGET.reset();
+    ZONE_MANUAL_CHANGES.reset();
+    HOME_AUTO_ACTIONS.reset();
+    ZONE_REJECTED_READINGS.reset();
+    ZONE_HEATING_SECONDS.reset();
+    DEVICE_ZONE_INFO.reset();
+    BRIDGE_CONNECTED.reset();
+    DEVICE_FAULT.reset();
+    ZONE_INFO.reset();
+    ZONE_HEATING_CIRCUIT.reset();
+    MOBILE_DEVICE_PUSH_ENABLED.reset();
+    HOME_GEOFENCING_OVERRIDDEN.reset();
+    HOME_TIMEZONE_INFO.reset();
+    HOME_LOCATION_INFO.reset();
+    HOME_TEMPERATURE_UNIT.reset();
+    BUILD_INFO.reset();
+    ZONE_GROUP_INFO.reset();
+    SCRAPE_DEADLINE_EXCEEDED.reset();
+    SCRAPE_RETRIES.reset();
+    HTTP_REQUESTS_INFLIGHT.reset();
+    WEATHER_DISABLED.reset();
+    API_REQUEST_RETRIES.reset();
+    CONSECUTIVE_SCRAPE_FAILURES.reset();
+    AUTHENTICATED.reset();
+    API_MAINTENANCE.reset();
+    API_CLOCK_SKEW.reset();
+    ACTIVE_CREDENTIAL.reset();
+    CIRCUIT_BREAKER_STATE.reset();
+
+    guard
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tado::model::{
+        PercentageDataType, TemperatureDataType, WeatherOutsideTemperatureApiResponse,
+        WeatherSolarIntensityApiResponse, WeatherStateApiResponse, WeatherStateDataType,
+    };
+
+    use super::*;
+
+    use rstest::*;
+
+    #[test]
+    fn test_set_weather_some() {
+        /*
+        GIVEN a weather response
+        WHEN set_weather is called
+        THEN the metrics are set
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let weather_response = WeatherApiResponse {
+            solarIntensity: WeatherSolarIntensityApiResponse {
+                dataType: PercentageDataType::Percentage,
+                percentage: 100.0,
+            },
+            outsideTemperature: WeatherOutsideTemperatureApiResponse {
+                dataType: TemperatureDataType::Temperature,
+                celsius: 20.0,
+                fahrenheit: 68.0,
+                timestamp: None,
+            },
+            weatherState: Some(WeatherStateApiResponse {
+                dataType: WeatherStateDataType::WeatherState,
+                value: "CLOUDY_PARTLY".to_string(),
+            }),
+        };
+
+        // WHEN
+        set_weather(Some(&weather_response));
+
+        // THEN
+        // Check metrics
+        let metrics = prometheus::gather();
+
+        assert_eq!(metrics.len(), 4);
+        assert_eq!(metrics[0].get_name(), "tado_weather_solar_intensity_ratio");
+        assert_eq!(metrics[1].get_name(), "tado_weather_state");
+        assert_eq!(metrics[2].get_name(), "weather_outside_temperature");
+        assert_eq!(metrics[3].get_name(), "weather_solar_intensity");
+
+        // Check weather state metric
+        let weather_state_metric = metrics[1].get_metric();
+
+        assert_eq!(weather_state_metric.len(), 1);
+        assert_eq!(weather_state_metric[0].get_label()[0].get_name(), "type");
+        assert_eq!(
+            weather_state_metric[0].get_label()[0].get_value(),
+            "WEATHER_STATE"
+        );
+        assert_eq!(weather_state_metric[0].get_label()[1].get_name(), "value");
+        assert_eq!(
+            weather_state_metric[0].get_label()[1].get_value(),
+            "CLOUDY_PARTLY"
+        );
+        assert_eq!(weather_state_metric[0].get_gauge().get_value(), 1.0);
+
+        // Check outside temperature metric
+        let outside_temperature_metric = metrics[2].get_metric();
+
+        assert_eq!(outside_temperature_metric.len(), 2);
+
+        let outside_temp_celsius = &outside_temperature_metric[0];
+        let outside_temp_fahrenheit = &outside_temperature_metric[1];
+
+        assert_eq!(outside_temp_celsius.get_label().len(), 1);
+        assert_eq!(outside_temp_celsius.get_label()[0].get_name(), "unit");
+        assert_eq!(outside_temp_celsius.get_label()[0].get_value(), "celsius");
+        assert_eq!(outside_temp_celsius.get_gauge().get_value(), 20.0);
+        assert_eq!(outside_temp_fahrenheit.get_label().len(), 1);
+        assert_eq!(outside_temp_fahrenheit.get_label()[0].get_name(), "unit");
+        assert_eq!(
+            outside_temp_fahrenheit.get_label()[0].get_value(),
+            "fahrenheit"
+        );
+        assert_eq!(outside_temp_fahrenheit.get_gauge().get_value(), 68.0);
+
+        // Check solar intensity metrics
+        let solar_intensity_metric = metrics[3].get_metric();
+
+        assert_eq!(solar_intensity_metric.len(), 1);
+        assert_eq!(solar_intensity_metric[0].get_gauge().get_value(), 100.0);
+
+        let solar_intensity_ratio_metric = metrics[0].get_metric();
+
+        assert_eq!(solar_intensity_ratio_metric.len(), 1);
+        assert_eq!(
+            solar_intensity_ratio_metric[0].get_gauge().get_value(),
+            solar_intensity_metric[0].get_gauge().get_value() / 100.0
+        );
+    }
+
+    #[rstest(
+        namespace,
+        expected,
+        case("home", r#"home_zone_stale{zone="Office"} 1"#),
+        case("", r#"zone_stale{zone="Office"} 1"#),
+        case("tado", r#"tado_zone_stale{zone="Office"} 1"#)
+    )]
+    fn test_render_namespace(namespace: &str, expected: &str) {
+        /*
+        GIVEN a metric namespace
+        WHEN rendering the metrics
+        THEN the tado prefix of the metrics names is replaced, and the
+             unprefixed weather metrics are left as is
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        set_zone_stale("Office", true);
+        set_weather(Some(&WeatherApiResponse {
+            solarIntensity: WeatherSolarIntensityApiResponse {
+                dataType: PercentageDataType::Percentage,
+                percentage: 50.0,
+            },
+            outsideTemperature: WeatherOutsideTemperatureApiResponse {
+                dataType: TemperatureDataType::Temperature,
+                celsius: 20.0,
+                fahrenheit: 68.0,
+                timestamp: None,
+            },
+            weatherState: None,
+        }));
+        set_namespace(namespace).unwrap();
+
+        // WHEN
+        let output = String::from_utf8(render()).unwrap();
+
+        // THEN
+        assert!(output.lines().any(|line| line == expected));
+        assert!(output.contains("\nweather_solar_intensity 50\n"));
+        assert_eq!(output.contains("tado_"), namespace == "tado");
+    }
+
+    #[rstest(
+        allow,
+        deny,
+        expected_humidity,
+        expected_temperature,
+        expected_weather,
+        case(&[], &["tado_sensor_humidity_percentage"], false, true, true),
+        case(&["tado_sensor_*"], &[], true, true, false),
+        case(&["tado_sensor_*"], &["tado_sensor_humidity_*"], false, true, false),
+        case(&[], &[], true, true, true)
+    )]
+    fn test_render_metric_filter(
+        allow: &[&str],
+        deny: &[&str],
+        expected_humidity: bool,
+        expected_temperature: bool,
+        expected_weather: bool,
+    ) {
+        /*
+        GIVEN a zone with a temperature and a humidity, the weather, and a
+              filter of the metrics
+        WHEN rendering the metrics
+        THEN only the metrics allowed and not denied are rendered
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        SENSOR_TEMPERATURE
+            .with_label_values(&["Office", "HEATING", "celsius"])
+            .set(21.5);
+        SENSOR_HUMIDITY_PERCENTAGE
+            .with_label_values(&["Office", "HEATING"])
+            .set(45.0);
+        WEATHER_SOLAR_INTENSITY.with_label_values(&[]).set(50.0);
+        set_metric_filter(MetricFilter {
+            allow: allow.iter().map(|name| name.to_string()).collect(),
+            deny: deny.iter().map(|name| name.to_string()).collect(),
+        });
+
+        // WHEN
+        let output = String::from_utf8(render()).unwrap();
+
+        // THEN
+        assert_eq!(
+            output.contains("tado_sensor_humidity_percentage{"),
+            expected_humidity
+        );
+        assert_eq!(
+            output.contains("tado_sensor_temperature_value{"),
+            expected_temperature
+        );
+        assert_eq!(
+            output.contains("weather_solar_intensity "),
+            expected_weather
+        );
+    }
+
+    #[rstest(namespace, case("1home"), case("home-tado"), case("tado°"))]
+    fn test_set_namespace_invalid(namespace: &str) {
+        assert!(set_namespace(namespace).is_err());
+    }
+
+    #[test]
+    fn test_set_build_info() {
+        /*
+        GIVEN the exporter's build
+        WHEN set_build_info is called
+        THEN the build info metric reports its version
+        */
+
+        let _registry = lock_registry();
+
+        // WHEN
+        set_build_info();
+
+        // THEN
+        let output = String::from_utf8(render()).unwrap();
+        let expected = format!(
+            r#"tado_exporter_build_info{{git_sha="{}",rustc="{}",version="{}"}} 1"#,
+            env!("GIT_SHA"),
+            env!("RUSTC_VERSION"),
+            env!("CARGO_PKG_VERSION")
+        );
+
+        assert!(output.lines().any(|line| line == expected));
+    }
+
+    #[test]
+    fn test_set_devices() {
+        /*
+        GIVEN devices serving a zone, one of which is faulty, and two bridges
+              serving the whole home, one of which is offline
+        WHEN set_devices is called
+        THEN each device is mapped to its zone, or to the home, the
+             connectivity of the bridges is reported, and so is the fault of
+             the faulty valve
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let devices = vec![
+            DeviceZoneResponse {
+                serial: "IB1234567890".to_string(),
+                zone: None,
+                device_type: "IB01".to_string(),
+                connected: Some(true),
+                fault: None,
+            },
+            DeviceZoneResponse {
+                serial: "IB0987654321".to_string(),
+                zone: None,
+                device_type: "IB01".to_string(),
+                connected: Some(false),
+                fault: None,
+            },
+            DeviceZoneResponse {
+                serial: "VA1234567890".to_string(),
+                zone: Some("Living room".to_string()),
+                device_type: "VA02".to_string(),
+                connected: Some(true),
+                fault: None,
+            },
+            DeviceZoneResponse {
+                serial: "VA0987654321".to_string(),
+                zone: Some("Bathroom".to_string()),
+                device_type: "VA02".to_string(),
+                connected: Some(true),
+                fault: Some("MOUNTING_ERROR".to_string()),
+            },
+        ];
+
+        // WHEN
+        set_devices(&devices);
+
+        // THEN
+        let output = String::from_utf8(render()).unwrap();
+
+        assert!(output.contains(r#"tado_device_zone_info{serial="IB1234567890",zone="home"} 1"#));
+        assert!(
+            output.contains(r#"tado_device_zone_info{serial="VA1234567890",zone="Living room"} 1"#)
+        );
+        assert!(output.contains(r#"tado_bridge_connected{serial="IB1234567890"} 1"#));
+        assert!(output.contains(r#"tado_bridge_connected{serial="IB0987654321"} 0"#));
+        assert!(!output.contains(r#"tado_bridge_connected{serial="VA1234567890"}"#));
+        assert!(
+            output.contains(r#"tado_device_fault{code="MOUNTING_ERROR",serial="VA0987654321"} 1"#)
+        );
+        assert!(
+            !output.contains(r#"tado_device_fault{code="MOUNTING_ERROR",serial="VA1234567890"}"#)
+        );
+    }
+
+    #[test]
+    fn test_set_zone_info() {
+        /*
+        GIVEN the zones of a home, with their type and creation date, one of
+              them assigned to a heating circuit
+        WHEN set_zone_info is called
+        THEN the metadata of each zone is reported, along with the heating
+             circuit of the zone assigned to one
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let zones: Vec<ZonesApiResponse> = serde_json::from_str(
+            r#"[
+                {
+                    "id": 1,
+                    "name": "Living room",
+                    "type": "HEATING",
+                    "dateCreated": "2019-11-02T09:32:51.421Z",
+                    "heatingCircuit": 1,
+                    "deviceTypes": ["RU02", "VA02"],
+                    "devices": [{ "deviceType": "RU02", "serialNo": "RU1234567890" }],
+                    "reportAvailable": false,
+                    "showScheduleSetup": false,
+                    "supportsDazzle": true
+                },
+                {
+                    "id": 0,
+                    "name": "Hot Water",
+                    "type": "HOT_WATER",
+                    "dateCreated": "2019-11-02T09:30:12.118Z",
+                    "deviceTypes": ["BU01"],
+                    "devices": []
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        // WHEN
+        set_zone_info(&zones, ZoneLabel::Name);
+
+        // THEN
+        let output = String::from_utf8(render()).unwrap();
+
+        assert!(output.contains(
+            r#"tado_zone_info{date_created="2019-11-02T09:32:51.421Z",name="Living room",type="HEATING",zone="Living room",zone_id="1"} 1"#
+        ));
+        assert!(output.contains(
+            r#"tado_zone_info{date_created="2019-11-02T09:30:12.118Z",name="Hot Water",type="HOT_WATER",zone="Hot Water",zone_id="0"} 1"#
+        ));
+        assert!(output.contains(r#"tado_zone_heating_circuit{circuit="1",zone="Living room"} 1"#));
+        assert!(!output.contains(r#"tado_zone_heating_circuit{circuit="1",zone="Hot Water"}"#));
+    }
+
+    #[rstest(celsius, expected,
+        case(21.53, 21.5),
+        case(21.56, 21.6),
+        // halves are rounded up, despite their binary representation
+        case(21.45, 21.5),
+        case(21.55, 21.6),
+        case(20.0, 20.0),
+        case(-2.45, -2.4),
+        case(-2.46, -2.5)
+    )]
+    fn test_round_like_app(celsius: f64, expected: f64) {
+        assert_eq!(round_like_app(celsius), expected);
+    }
+
+    #[rstest(
+        presence_locked,
+        geo_tracking,
+        expected,
+        case(true, None, 1),
+        case(true, Some(true), 1),
+        case(true, Some(false), 0),
+        case(false, None, 0),
+        case(false, Some(true), 0)
+    )]
+    fn test_set_home_state(presence_locked: bool, geo_tracking: Option<bool>, expected: u8) {
+        /*
+        GIVEN a home whose presence is locked or not, and a mobile device
+              tracking its location or not, or no mobile devices collected
+        WHEN set_home_state is called
+        THEN geofencing is reported overridden only when the presence is
+             locked and may otherwise follow the mobile devices
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let home_state: HomeStateApiResponse = serde_json::from_str(&format!(
+            r#"{{ "presence": "HOME", "presenceLocked": {} }}"#,
+            presence_locked
+        ))
+        .unwrap();
+        let mobile_devices: Vec<MobileDeviceApiResponse> = match geo_tracking {
+            Some(geo_tracking) => serde_json::from_str(&format!(
+                r#"[{{ "name": "Phone", "settings": {{ "geoTrackingEnabled": {} }} }}]"#,
+                geo_tracking
+            ))
+            .unwrap(),
+            None => Vec::new(),
+        };
+
+        // WHEN
+        set_home_state(Some(&home_state), &mobile_devices);
+        let with_home_state = String::from_utf8(render()).unwrap();
+
+        set_home_state(None, &mobile_devices);
+        let without_home_state = String::from_utf8(render()).unwrap();
+
+        // THEN
+        assert!(with_home_state
+            .lines()
+            .any(|l| l == format!("tado_home_geofencing_overridden {}", expected)));
+        assert!(!without_home_state
+            .lines()
+            .any(|l| l.starts_with("tado_home_geofencing_overridden ")));
+    }
+
+    #[test]
+    fn test_set_mobile_devices() {
+        /*
+        GIVEN a mobile device with presence notifications enabled, one with
+              them disabled and one not reporting its notification settings
+        WHEN set_mobile_devices is called
+        THEN the notification settings are reported for the first two only
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mobile_devices: Vec<MobileDeviceApiResponse> = serde_json::from_str(
+            r#"[
+                {
+                    "name": "Phone",
+                    "id": 1234,
+                    "settings": {
+                        "geoTrackingEnabled": true,
+                        "pushNotifications": {
+                            "lowBatteryReminder": true,
+                            "awayModeReminder": true,
+                            "homeModeReminder": false
+                        }
+                    }
+                },
+                {
+                    "name": "Tablet",
+                    "id": 5678,
+                    "settings": {
+                        "geoTrackingEnabled": false,
+                        "pushNotifications": {
+                            "lowBatteryReminder": true,
+                            "awayModeReminder": false,
+                            "homeModeReminder": false
+                        }
+                    }
+                },
+                {
+                    "name": "Watch",
+                    "id": 9012,
+                    "settings": { "geoTrackingEnabled": false }
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        // WHEN
+        set_mobile_devices(&mobile_devices);
+
+        // THEN
+        let output = String::from_utf8(render()).unwrap();
+
+        assert!(output.contains(r#"tado_mobile_device_push_enabled{name="Phone"} 1"#));
+        assert!(output.contains(r#"tado_mobile_device_push_enabled{name="Tablet"} 0"#));
+        assert!(!output.contains(r#"tado_mobile_device_push_enabled{name="Watch"}"#));
+    }
+
+    fn zone(name: &str, state: &str) -> ZoneStateResponse {
+        ZoneStateResponse {
+            name: name.to_string(),
+            state_response: serde_json::from_str(state).unwrap(),
+            scheduled_setting: None,
+            away_setting: None,
+        }
+    }
+
+    #[test]
+    fn test_set_zones_off() {
+        /*
+        GIVEN a heating zone that is turned off after being on
+        WHEN set_zones is called with both states
+        THEN only the metrics present in the latest state are set
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let zone_on = zone(
+            "Office",
+            r#"{
+                "setting": {
+                    "type": "HEATING",
+                    "temperature": { "celsius": 21.0, "fahrenheit": 69.8 }
+                },
+                "activityDataPoints": {
+                    "heatingPower": { "percentage": 30.0 }
+                },
+                "sensorDataPoints": {
+                    "insideTemperature": { "celsius": 19.5, "fahrenheit": 67.1 },
+                    "humidity": { "percentage": 51.0 }
+                }
+            }"#,
+        );
+        let zone_off = zone(
+            "Office",
+            r#"{
+                "setting": { "type": "HEATING", "temperature": null },
+                "activityDataPoints": {},
+                "sensorDataPoints": {
+                    "insideTemperature": { "celsius": 19.4, "fahrenheit": 66.9 },
+                    "humidity": { "percentage": 51.2 }
+                },
+                "openWindow": null
+            }"#,
+        );
+
+        // WHEN
+        set_zones(&[zone_on]);
+        set_zones(&[zone_off]);
+
+        // THEN
+        let metrics = prometheus::gather();
+        let names: Vec<&str> = metrics.iter().map(|m| m.get_name()).collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "tado_sensor_humidity_percentage",
+                "tado_sensor_temperature_value",
+                "tado_sensor_window_opened",
+                "tado_zone_power_on",
+            ]
+        );
+        assert_eq!(metrics[0].get_metric()[0].get_gauge().get_value(), 51.2);
+        assert_eq!(metrics[1].get_metric()[0].get_gauge().get_value(), 19.4);
+        assert_eq!(metrics[2].get_metric()[0].get_gauge().get_value(), 0.0);
+        assert_eq!(metrics[3].get_metric()[0].get_gauge().get_value(), 0.0);
+    }
+
+    #[rstest(
+        setting,
+        expected_power_on,
+        case(
+            r#"{ "type": "HEATING", "power": "OFF", "temperature": { "celsius": 5.0, "fahrenheit": 41.0 } }"#,
+            false
+        ),
+        case(r#"{ "type": "HEATING", "power": "OFF", "temperature": null }"#, false),
+        case(r#"{ "type": "HOT_WATER", "power": "ON", "temperature": null }"#, true),
+        case(
+            r#"{ "type": "HEATING", "power": "ON", "temperature": { "celsius": 21.0, "fahrenheit": 69.8 } }"#,
+            true
+        )
+    )]
+    fn test_set_zones_power(setting: &str, expected_power_on: bool) {
+        /*
+        GIVEN a zone explicitly switched on or off
+        WHEN set_zones is called
+        THEN the power gauge reports it, and an off zone has no target nor
+             heating power
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let zone = zone(
+            "Office",
+            &format!(
+                r#"{{
+                    "setting": {},
+                    "activityDataPoints": {{
+                        "heatingPower": {{ "percentage": 0.0 }}
+                    }}
+                }}"#,
+                setting
+            ),
+        );
+        let has_temperature = zone.state_response.setting.temperature.is_some();
+
+        // WHEN
+        set_zones(&[zone]);
+
+        // THEN
+        let output = String::from_utf8(render()).unwrap();
+
+        assert!(output.contains(&format!(
+            r#"tado_zone_power_on{{zone="Office"}} {}"#,
+            if expected_power_on { 1 } else { 0 }
+        )));
+        assert_eq!(
+            output.contains("tado_activity_heating_power_percentage"),
+            expected_power_on
+        );
+        assert_eq!(
+            output.contains("tado_setting_temperature_value"),
+            expected_power_on && has_temperature
+        );
+    }
+
+    #[test]
+    fn test_set_open_window_ages() {
+        /*
+        GIVEN a zone with a window detected open 5 minutes ago
+        WHEN set_open_window_ages is called, then again once it is closed
+        THEN the age of the open window is reported, then removed
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let now = "2022-11-21T11:20:32Z".parse::<DateTime<Utc>>().unwrap();
+        let window_opened = zone(
+            "Office",
+            r#"{
+                "setting": { "type": "HEATING", "temperature": null },
+                "openWindow": {
+                    "detectedTime": "2022-11-21T11:15:32Z",
+                    "durationInSeconds": 900,
+                    "expiry": "2022-11-21T11:30:32Z",
+                    "remainingTimeInSeconds": 600
+                }
+            }"#,
+        );
+        let window_closed = zone(
+            "Office",
+            r#"{ "setting": { "type": "HEATING", "temperature": null } }"#,
+        );
+
+        // WHEN
+        set_open_window_ages(&[window_opened], now);
+        let opened = String::from_utf8(render()).unwrap();
+
+        set_open_window_ages(&[window_closed], now);
+        let closed = String::from_utf8(render()).unwrap();
+
+        // THEN
+        assert!(opened.contains(r#"tado_zone_open_window_detected_age_seconds{zone="Office"} 300"#));
+        assert!(!closed.contains("tado_zone_open_window_detected_age_seconds"));
+    }
+
+    #[rstest(
+        percentage,
+        expected,
+        case(Some(29.9), Some(1)),
+        case(Some(30.0), Some(0)),
+        case(Some(45.0), Some(0)),
+        case(Some(60.0), Some(0)),
+        case(Some(60.1), Some(1)),
+        case(None, None)
+    )]
+    fn test_set_humidity_out_of_range(percentage: Option<f64>, expected: Option<u8>) {
+        /*
+        GIVEN a zone with a humidity around the 30..=60 comfort band, or without
+              a humidity sensor
+        WHEN set_humidity_out_of_range is called
+        THEN the zone is flagged outside of the band, but not at its bounds,
+             and zones without humidity are omitted
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let humidity = match percentage {
+            Some(percentage) => format!(r#"{{ "percentage": {} }}"#, percentage),
+            None => "null".to_string(),
+        };
+        let zone = zone(
+            "Bathroom",
+            &format!(
+                r#"{{
+                    "setting": {{ "type": "HEATING", "temperature": null }},
+                    "sensorDataPoints": {{ "insideTemperature": null, "humidity": {} }}
+                }}"#,
+                humidity
+            ),
+        );
+
+        // WHEN
+        set_humidity_out_of_range(&[zone], 30.0, 60.0);
+
+        // THEN
+        let output = String::from_utf8(render()).unwrap();
+
+        match expected {
+            Some(expected) => assert!(output.lines().any(|l| l
+                == format!(
+                    r#"tado_zone_humidity_out_of_range{{zone="Bathroom"}} {}"#,
+                    expected
+                ))),
+            None => assert!(!output.contains("tado_zone_humidity_out_of_range{")),
+        }
+    }
+
+    #[test]
+    fn test_set_zones_unknown_ac_power() {
+        /*
+        GIVEN an AC zone reporting an unknown ac power value
+        WHEN set_zones is called
+        THEN the ac power metric is not set
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let zone_ac = zone(
+            "Bedroom",
+            r#"{
+                "setting": { "type": "AIR_CONDITIONING", "temperature": null },
+                "activityDataPoints": {
+                    "acPower": { "value": "STANDBY" }
+                },
+                "sensorDataPoints": {}
+            }"#,
+        );
+
+        // WHEN
+        set_zones(&[zone_ac]);
+
+        // THEN
+        let metrics = prometheus::gather();
+        let names: Vec<&str> = metrics.iter().map(|m| m.get_name()).collect();
+
+        assert_eq!(
+            names,
+            vec!["tado_sensor_window_opened", "tado_zone_power_on"]
+        );
+    }
+
+    #[test]
+    fn test_set_zones_scheduled_target() {
+        /*
+        GIVEN a zone with a scheduled setting and a zone without schedule
+        WHEN set_zones is called
+        THEN the scheduled target is only reported for the scheduled zone
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let state = r#"{
+            "setting": { "type": "HEATING", "temperature": null },
+            "activityDataPoints": {},
+            "sensorDataPoints": {}
+        }"#;
+        let scheduled = ZoneStateResponse {
+            scheduled_setting: Some(
+                serde_json::from_str(
+                    r#"{ "type": "HEATING", "temperature": { "celsius": 20.5, "fahrenheit": 68.9 } }"#,
+                )
+                .unwrap(),
+            ),
+            ..zone("Office", state)
+        };
+        let manual = zone("Garage", state);
+
+        // WHEN
+        set_zones(&[scheduled, manual]);
+
+        // THEN
+        let metrics = prometheus::gather();
+        let scheduled_target = metrics
+            .iter()
+            .find(|m| m.get_name() == "tado_zone_scheduled_target_celsius")
+            .unwrap()
+            .get_metric();
+
+        assert_eq!(scheduled_target.len(), 1);
+        assert_eq!(scheduled_target[0].get_label()[0].get_value(), "Office");
+        assert_eq!(scheduled_target[0].get_gauge().get_value(), 20.5);
+    }
+
+    #[test]
     fn test_set_weather_none() {
         /*
         GIVEN no weather response
@@ -322,6 +2183,8 @@ mod tests {
         THEN the metrics are not set
         */
 
+        let _registry = lock_registry();
+
         // WHEN
         set_weather(None);
 
@@ -1,3 +1,23 @@
+pub mod backfill;
+pub mod breaker;
+pub mod cache;
 pub mod client;
+pub mod clock;
+pub mod collector;
+pub mod credentials;
+pub mod debug;
+pub mod endpoints;
+pub mod history;
+pub mod homeassistant;
+pub mod logs;
 pub mod metrics;
 pub mod model;
+pub mod pushgateway;
+#[cfg(test)]
+mod replay;
+pub mod router;
+pub mod schedule;
+pub mod state;
+pub mod sun;
+pub mod ticker;
+pub mod token_store;
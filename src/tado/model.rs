@@ -1,8 +1,24 @@
-use serde_derive::Deserialize;
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+// tado° doesn't document its API and adds or drops fields over time. Unknown
+// fields are ignored, and fields the exporter can do without default when
+// missing, so a change only degrades the metrics relying on them rather than
+// failing the whole scrape.
 
 #[derive(Deserialize, Debug)]
 pub struct AuthApiResponse {
     pub access_token: String,
+    #[serde(default)]
+    pub expires_in: u64,
+}
+
+// The error of a rejected authentication, as defined by OAuth 2.0, e.g.
+// invalid_grant for wrong credentials or invalid_client for a client id
+// tado° doesn't accept.
+#[derive(Deserialize, Debug)]
+pub struct AuthErrorApiResponse {
+    pub error: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -13,99 +29,646 @@ pub struct MeApiResponse {
 #[derive(Deserialize, Debug)]
 pub struct HomesApiResponse {
     pub id: i32,
+    #[serde(default)]
+    pub name: String,
 }
 
 #[derive(Deserialize, Debug)]
+#[allow(non_snake_case)]
+pub struct HomeApiResponse {
+    pub dateTimeZone: String, // IANA time zone, e.g. Europe/Paris
+    pub geolocation: Option<GeolocationApiResponse>,
+    #[serde(default)]
+    pub temperatureUnit: Option<String>, // CELSIUS or FAHRENHEIT
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct GeolocationApiResponse {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(non_snake_case)]
 pub struct ZonesApiResponse {
     pub id: i32,
     pub name: String,
+    #[serde(rename = "type", default)]
+    pub zoneType: String, // e.g. HEATING, HOT_WATER, AIR_CONDITIONING
+    #[serde(default)]
+    pub dateCreated: String, // RFC 3339 timestamp
+    // only reported for homes with several heating circuits
+    #[serde(default)]
+    pub heatingCircuit: Option<i32>,
+    #[serde(default)]
+    pub devices: Vec<DeviceApiResponse>,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Debug)]
+#[allow(non_snake_case)]
+pub struct DeviceApiResponse {
+    pub serialNo: String,
+    #[serde(default)]
+    pub deviceType: String, // e.g. RU02, VA02, IB01
+    // only reported by the devices endpoint
+    pub connectionState: Option<DeviceConnectionStateApiResponse>,
+    // e.g. CALIBRATED, or MOUNTING_ERROR for a valve which couldn't be
+    // mounted, only reported by the devices endpoint for valves
+    pub mountingStateWithError: Option<String>,
+}
+
+impl DeviceApiResponse {
+    /// The fault code the device reports, if any.
+    pub fn fault(&self) -> Option<String> {
+        self.mountingStateWithError
+            .clone()
+            .filter(|state| state.ends_with("_ERROR"))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeviceConnectionStateApiResponse {
+    pub value: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct MobileDeviceApiResponse {
+    pub name: String,
+    #[serde(default)]
+    pub settings: MobileDeviceSettingsApiResponse,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[allow(non_snake_case)]
+pub struct MobileDeviceSettingsApiResponse {
+    // absent for devices that never enabled notifications
+    pub pushNotifications: Option<MobileDevicePushNotificationsApiResponse>,
+    // whether the device shares its location for geofencing
+    #[serde(default)]
+    pub geoTrackingEnabled: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[allow(non_snake_case)]
+pub struct MobileDevicePushNotificationsApiResponse {
+    #[serde(default)]
+    pub awayModeReminder: bool,
+    #[serde(default)]
+    pub homeModeReminder: bool,
+}
+
+impl MobileDeviceApiResponse {
+    /// Whether the presence-based notifications (reminders to switch the
+    /// home to away or home mode) are enabled, if the device reports it.
+    pub fn push_enabled(&self) -> Option<bool> {
+        self.settings
+            .pushNotifications
+            .as_ref()
+            .map(|push| push.awayModeReminder || push.homeModeReminder)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[allow(non_snake_case)]
+pub struct HomeStateApiResponse {
+    pub presence: Option<String>, // HOME or AWAY
+    // whether the presence was set manually, rather than by geofencing
+    #[serde(default)]
+    pub presenceLocked: bool,
+}
+
+/// A zone state response, decoded leniently so a single zone of a type the
+/// model can't represent doesn't fail the whole scrape.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DecodedZoneStateApiResponse {
+    Supported(Box<ZoneStateApiResponse>),
+    Unsupported(UnsupportedZoneStateApiResponse),
+}
+
+/// The state of a zone whose setting doesn't fit the model, e.g. of a zone
+/// type tado° added after the exporter.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct UnsupportedZoneStateApiResponse {
+    pub setting: serde_json::Value,
+}
+
+impl UnsupportedZoneStateApiResponse {
+    /// The type of the zone's setting, as reported by tado°.
+    pub fn setting_type(&self) -> String {
+        self.setting
+            .get("type")
+            .and_then(|setting_type| setting_type.as_str())
+            .unwrap_or("UNKNOWN")
+            .to_string()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[allow(non_snake_case)]
 pub struct ZoneStateApiResponse {
+    pub tadoMode: Option<String>, // HOME or AWAY, the presence of the home
     pub setting: ZoneStateSettingApiResponse,
+    #[serde(default)]
     pub activityDataPoints: ZoneStateActivityDataPointsApiResponse,
-    pub sensorDataPoints: ZoneStateSensorDataPointsApiResponse,
+    // absent for zones without sensors, e.g. hot water
+    pub sensorDataPoints: Option<ZoneStateSensorDataPointsApiResponse>,
     pub openWindow: Option<ZoneStateOpenWindowApiResponse>,
+    pub overlay: Option<ZoneStateOverlayApiResponse>,
+    // absent for zones without an upcoming schedule change
+    pub nextScheduleChange: Option<ZoneStateNextScheduleChangeApiResponse>,
+}
+
+impl ZoneStateApiResponse {
+    /// Whether the zone is switched on and currently using heating power.
+    pub fn is_heating(&self) -> bool {
+        self.setting.is_powered_on()
+            && self
+                .activityDataPoints
+                .heatingPower
+                .as_ref()
+                .is_some_and(|heating_power| heating_power.percentage > 0.0)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ZoneStateNextScheduleChangeApiResponse {
+    pub start: String, // RFC 3339 timestamp
+    pub setting: Option<ZoneStateSettingApiResponse>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[allow(non_snake_case)]
 pub struct ZoneStateOpenWindowApiResponse {
+    // only the presence of an open window is reported
+    #[serde(default)]
     pub detectedTime: String, // RFC 3339 timestamp
+    #[serde(default)]
     pub durationInSeconds: i32,
+    #[serde(default)]
     pub expiry: String,
+    #[serde(default)]
     pub remainingTimeInSeconds: i32,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[allow(non_snake_case)]
+pub struct ZoneStateOverlayApiResponse {
+    #[serde(rename = "type", default)]
+    pub overlayType: String,
+    pub setting: ZoneStateSettingApiResponse,
+    pub termination: Option<ZoneStateOverlayTerminationApiResponse>,
+}
+
+impl ZoneStateOverlayApiResponse {
+    /// Whether the overlay is still reported although it should have ended,
+    /// i.e. it has no time left or expired before `now`. Overlays lasting
+    /// until the next manual change never expire.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        let Some(termination) = &self.termination else {
+            return false;
+        };
+
+        let no_time_left = termination
+            .remainingTimeInSeconds
+            .is_some_and(|remaining| remaining <= 0);
+        let expired = self.ends_at().is_some_and(|expiry| expiry < now);
+
+        no_time_left || expired
+    }
+
+    /// When the overlay ends, e.g. at the end of a boost timer. Overlays
+    /// lasting until the next manual change have no end.
+    pub fn ends_at(&self) -> Option<DateTime<Utc>> {
+        self.termination
+            .as_ref()?
+            .expiry
+            .as_deref()
+            .and_then(|expiry| DateTime::parse_from_rfc3339(expiry).ok())
+            .map(|expiry| expiry.with_timezone(&Utc))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[allow(non_snake_case)]
+pub struct ZoneStateOverlayTerminationApiResponse {
+    #[serde(rename = "type", default)]
+    pub terminationType: String, // MANUAL, TIMER or TADO_MODE
+    pub expiry: Option<String>, // RFC 3339 timestamp
+    pub remainingTimeInSeconds: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AwayConfigurationApiResponse {
+    // absent when the away setting is adjusted automatically
+    pub setting: Option<ZoneStateSettingApiResponse>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[allow(non_snake_case)]
 pub struct ZoneStateSettingApiResponse {
     #[serde(rename = "type")]
     pub deviceType: String,
+    pub power: Option<String>, // ON or OFF
     pub temperature: Option<ZoneStateSettingTemperatureApiResponse>,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+impl ZoneStateSettingApiResponse {
+    /// Whether the zone is switched on. Without an explicit power, a zone
+    /// setting no temperature is considered off.
+    pub fn is_powered_on(&self) -> bool {
+        match self.power.as_deref() {
+            Some(power) => power != "OFF",
+            None => self.temperature.is_some(),
+        }
+    }
+
+    /// Whether both settings switch the zone off, or set the same
+    /// temperature.
+    pub fn same_target(&self, other: &ZoneStateSettingApiResponse) -> bool {
+        let celsius = |setting: &ZoneStateSettingApiResponse| {
+            setting
+                .temperature
+                .as_ref()
+                .filter(|_| setting.is_powered_on())
+                .map(|temperature| temperature.celsius)
+        };
+
+        match (celsius(self), celsius(other)) {
+            (Some(a), Some(b)) => (a - b).abs() < 0.05,
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct ZoneStateSettingTemperatureApiResponse {
     pub celsius: f64,
     pub fahrenheit: f64,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
 #[allow(non_snake_case)]
 pub struct ZoneStateActivityDataPointsApiResponse {
     pub heatingPower: Option<ActivityDataPointsHeatingPowerApiResponse>,
     pub acPower: Option<ActivityDataPointsAcPowerApiResponse>,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+impl ZoneStateActivityDataPointsApiResponse {
+    /// When the activity was last reported, the latest of the heating and AC
+    /// power timestamps.
+    pub fn reported_at(&self) -> Option<DateTime<Utc>> {
+        let heating_power = self
+            .heatingPower
+            .as_ref()
+            .and_then(|heating_power| heating_power.timestamp.as_deref());
+        let ac_power = self
+            .acPower
+            .as_ref()
+            .and_then(|ac_power| ac_power.timestamp.as_deref());
+
+        [heating_power, ac_power]
+            .iter()
+            .flatten()
+            .filter_map(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+            .max()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct ActivityDataPointsHeatingPowerApiResponse {
     pub percentage: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>, // RFC 3339 timestamp
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct ActivityDataPointsAcPowerApiResponse {
     pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>, // RFC 3339 timestamp
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[allow(non_snake_case)]
 pub struct ZoneStateSensorDataPointsApiResponse {
     pub insideTemperature: Option<SensorDataPointsInsideTemperatureApiResponse>,
     pub humidity: Option<SensorDataPointsHumidityApiResponse>,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct SensorDataPointsInsideTemperatureApiResponse {
     pub celsius: f64,
     pub fahrenheit: f64,
+    pub timestamp: Option<String>, // RFC 3339 timestamp
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct SensorDataPointsHumidityApiResponse {
     pub percentage: f64,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
 #[allow(non_snake_case)]
 pub struct WeatherApiResponse {
     pub solarIntensity: WeatherSolarIntensityApiResponse,
     pub outsideTemperature: WeatherOutsideTemperatureApiResponse,
+    pub weatherState: Option<WeatherStateApiResponse>,
 }
-#[derive(Deserialize, Debug, PartialEq)]
+
+// The weather data points carry a `type` discriminator. Each one only accepts
+// the type its fields are parsed as, so a data point tado° reassigns to
+// another type fails to deserialize instead of being silently misread.
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PercentageDataType {
+    Percentage,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TemperatureDataType {
+    Temperature,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WeatherStateDataType {
+    WeatherState,
+}
+
+impl WeatherStateDataType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WeatherStateDataType::WeatherState => "WEATHER_STATE",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[allow(non_snake_case)]
 pub struct WeatherSolarIntensityApiResponse {
+    #[serde(rename = "type")]
+    pub dataType: PercentageDataType,
     pub percentage: f64,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[allow(non_snake_case)]
 pub struct WeatherOutsideTemperatureApiResponse {
+    #[serde(rename = "type")]
+    pub dataType: TemperatureDataType,
     pub fahrenheit: f64,
     pub celsius: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>, // RFC 3339 timestamp
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[allow(non_snake_case)]
+pub struct WeatherStateApiResponse {
+    #[serde(rename = "type")]
+    pub dataType: WeatherStateDataType,
+    pub value: String, // e.g. SUN, CLOUDY_PARTLY, RAIN
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ActiveTimetableApiResponse {
+    pub id: i32,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[allow(non_snake_case)]
+pub struct TimetableBlockApiResponse {
+    pub dayType: String,
+    pub start: String, // HH:MM, in the home's local time
+    pub end: String,   // HH:MM, 00:00 meaning the end of the day
+    pub setting: ZoneStateSettingApiResponse,
+}
+
+/// The measurements of a zone over a whole day, as graphed in the tado° app.
+#[derive(Deserialize, Debug, PartialEq)]
+#[allow(non_snake_case)]
+pub struct DayReportApiResponse {
+    pub zoneType: String,
+    pub measuredData: DayReportMeasuredDataApiResponse,
+    pub settings: DayReportIntervalsApiResponse<Option<ZoneStateSettingApiResponse>>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[allow(non_snake_case)]
+pub struct DayReportMeasuredDataApiResponse {
+    pub insideTemperature: DayReportDataPointsApiResponse<ZoneStateSettingTemperatureApiResponse>,
+    // a ratio from 0 to 1, rather than a percentage
+    pub humidity: DayReportDataPointsApiResponse<f64>,
 }
 
+#[derive(Deserialize, Debug, PartialEq)]
+#[allow(non_snake_case)]
+pub struct DayReportDataPointsApiResponse<T> {
+    #[serde(default = "Vec::new")]
+    pub dataPoints: Vec<DayReportDataPointApiResponse<T>>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct DayReportDataPointApiResponse<T> {
+    pub timestamp: String, // RFC 3339 timestamp
+    pub value: T,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[allow(non_snake_case)]
+pub struct DayReportIntervalsApiResponse<T> {
+    #[serde(default = "Vec::new")]
+    pub dataIntervals: Vec<DayReportIntervalApiResponse<T>>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct DayReportIntervalApiResponse<T> {
+    pub from: String, // RFC 3339 timestamp
+    pub to: String,   // RFC 3339 timestamp
+    pub value: T,
+}
+
+#[derive(Serialize, Clone)]
 pub struct ZoneStateResponse {
     pub name: String,
     pub state_response: ZoneStateApiResponse,
+    // The setting of the zone's schedule block active at the time of the
+    // scrape, if schedules are collected and the zone has one.
+    pub scheduled_setting: Option<ZoneStateSettingApiResponse>,
+    // The setting the zone is configured to apply while the home is away,
+    // only retrieved while it is.
+    pub away_setting: Option<ZoneStateSettingApiResponse>,
+}
+
+/// A zone skipped by the scrape, as its state doesn't fit the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedZoneResponse {
+    pub name: String,
+    pub setting_type: String,
+}
+
+/// A single-glance summary of the home, e.g. for a dashboard header.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HomeSummary {
+    // HOME or AWAY, if any zone reports it.
+    pub presence: Option<String>,
+    pub outside_temperature_celsius: f64,
+    pub zone_count: usize,
+    // The zones switched on and currently using heating power.
+    pub heating_zone_count: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DeviceZoneResponse {
+    pub serial: String,
+    // The name of the zone the device serves, if any: some devices (e.g. the
+    // internet bridge) serve the whole home.
+    pub zone: Option<String>,
+    pub device_type: String,
+    // Whether the device is connected to tado°, if reported.
+    pub connected: Option<bool>,
+    // The fault code the device reports, e.g. MOUNTING_ERROR, if any.
+    pub fault: Option<String>,
+}
+
+impl DeviceZoneResponse {
+    /// Whether the device is an internet bridge, through which all the other
+    /// devices of the home reach tado°.
+    pub fn is_bridge(&self) -> bool {
+        self.device_type.starts_with("IB")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_state_missing_optional_fields() {
+        /*
+        GIVEN a zone state without activity data points, and an open window
+              and overlay stripped of their details
+        WHEN parsing it
+        THEN the missing fields default instead of failing
+        */
+
+        let actual: ZoneStateApiResponse = serde_json::from_str(
+            r#"{
+                "setting": { "type": "HEATING", "temperature": null },
+                "openWindow": {},
+                "overlay": { "setting": { "type": "HEATING" } },
+                "newlyAddedField": { "enabled": true }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(actual.activityDataPoints, Default::default());
+        assert_eq!(actual.sensorDataPoints, None);
+        assert_eq!(
+            actual.openWindow,
+            Some(ZoneStateOpenWindowApiResponse {
+                detectedTime: "".to_string(),
+                durationInSeconds: 0,
+                expiry: "".to_string(),
+                remainingTimeInSeconds: 0,
+            })
+        );
+        assert_eq!(actual.overlay.unwrap().overlayType, "");
+    }
+
+    #[test]
+    fn test_decoded_zone_state_unsupported() {
+        /*
+        GIVEN a zone state whose setting doesn't fit the model, and one whose
+              setting does
+        WHEN parsing them leniently
+        THEN the first is unsupported with its setting type, the second is
+             supported
+        */
+
+        let unsupported: DecodedZoneStateApiResponse = serde_json::from_str(
+            r#"{
+                "setting": {
+                    "type": "VENTILATION",
+                    "power": "ON",
+                    "temperature": { "value": 3, "unit": "LEVEL" }
+                }
+            }"#,
+        )
+        .unwrap();
+        let supported: DecodedZoneStateApiResponse = serde_json::from_str(
+            r#"{ "setting": { "type": "HEATING", "power": "OFF", "temperature": null } }"#,
+        )
+        .unwrap();
+
+        match unsupported {
+            DecodedZoneStateApiResponse::Unsupported(state) => {
+                assert_eq!(state.setting_type(), "VENTILATION")
+            }
+            other => panic!("expected an unsupported zone state, got {:?}", other),
+        }
+        assert!(matches!(
+            supported,
+            DecodedZoneStateApiResponse::Supported(_)
+        ));
+    }
+
+    #[test]
+    fn test_overlay_is_expired() {
+        /*
+        GIVEN overlays ending manually, in the future, or in the past
+        WHEN checking whether they expired
+        THEN only the overlays with no time left or a past expiry expired
+        */
+
+        let now = "2024-01-14T18:05:00Z".parse::<DateTime<Utc>>().unwrap();
+        let overlay = |termination: &str| -> ZoneStateOverlayApiResponse {
+            serde_json::from_str(&format!(
+                r#"{{ "setting": {{ "type": "HEATING" }}, "termination": {} }}"#,
+                termination
+            ))
+            .unwrap()
+        };
+
+        assert!(!overlay("null").is_expired(now));
+        assert!(!overlay(r#"{ "type": "MANUAL" }"#).is_expired(now));
+        assert!(!overlay(
+            r#"{ "type": "TIMER", "expiry": "2024-01-14T18:30:00Z", "remainingTimeInSeconds": 1500 }"#
+        )
+        .is_expired(now));
+        assert!(overlay(
+            r#"{ "type": "TIMER", "expiry": "2024-01-14T17:50:00Z", "remainingTimeInSeconds": 0 }"#
+        )
+        .is_expired(now));
+        assert!(
+            overlay(r#"{ "type": "TIMER", "expiry": "2024-01-14T17:50:00Z" }"#).is_expired(now)
+        );
+        assert!(overlay(r#"{ "type": "TIMER", "remainingTimeInSeconds": -60 }"#).is_expired(now));
+    }
+
+    #[test]
+    fn test_zones_missing_optional_fields() {
+        /*
+        GIVEN zones without devices, or with devices missing their type
+        WHEN parsing them
+        THEN the missing fields default instead of failing
+        */
+
+        let actual: Vec<ZonesApiResponse> = serde_json::from_str(
+            r#"[
+                { "id": 1, "name": "Office" },
+                { "id": 2, "name": "Kitchen", "devices": [{ "serialNo": "VA1234567890" }] }
+            ]"#,
+        )
+        .unwrap();
+
+        assert!(actual[0].devices.is_empty());
+        assert_eq!(actual[1].devices[0].serialNo, "VA1234567890");
+        assert_eq!(actual[1].devices[0].deviceType, "");
+    }
 }
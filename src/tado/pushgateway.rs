@@ -0,0 +1,189 @@
+use log::{error, info, warn};
+use prometheus::{Encoder, TextEncoder};
+use std::time::Duration;
+
+use super::metrics;
+
+/// Push the rendered metrics to a Prometheus Pushgateway, for deployments
+/// where Prometheus can't scrape the exporter directly.
+///
+/// Metrics are grouped under `job` and, when set, `instance`. Each push
+/// replaces the whole group, so series removed since the previous push also
+/// disappear from the gateway.
+pub struct Pusher {
+    http_client: reqwest::Client,
+    url: reqwest::Url,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl Pusher {
+    pub fn new(gateway_url: &str, job: &str, instance: &str) -> Result<Pusher, String> {
+        let mut url: reqwest::Url = gateway_url
+            .parse()
+            .map_err(|e| format!("invalid pushgateway url {}: {}", gateway_url, e))?;
+
+        {
+            let mut segments = url
+                .path_segments_mut()
+                .map_err(|_| format!("invalid pushgateway url {}", gateway_url))?;
+            segments.pop_if_empty().extend(&["metrics", "job", job]);
+            if !instance.is_empty() {
+                segments.extend(&["instance", instance]);
+            }
+        }
+
+        Ok(Pusher {
+            http_client: reqwest::Client::new(),
+            url,
+            retries: 1,
+            retry_delay: Duration::from_secs(1),
+        })
+    }
+
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> Pusher {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Push the current metrics, retrying failed pushes before giving up.
+    pub async fn push(&self) -> Result<(), reqwest::Error> {
+        let mut attempt = 0;
+
+        loop {
+            match self.push_once().await {
+                Ok(()) => {
+                    info!("pushed metrics to {}", self.url);
+                    return Ok(());
+                }
+                Err(e) if attempt < self.retries => {
+                    warn!("unable to push metrics, retrying: {}", e);
+                    attempt += 1;
+                    tokio::time::sleep(self.retry_delay).await;
+                }
+                Err(e) => {
+                    error!("unable to push metrics to {}: {}", self.url, e);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    async fn push_once(&self) -> Result<(), reqwest::Error> {
+        let encoder = TextEncoder::new();
+
+        self.http_client
+            .put(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, encoder.format_type())
+            .body(metrics::render())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+// Each test runs on its own runtime, so holding the registry lock across await
+// points cannot deadlock.
+#[cfg(test)]
+#[allow(clippy::await_holding_lock)]
+mod tests {
+    use super::*;
+
+    use crate::tado::metrics::{lock_registry, set_zone_stale};
+
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_new_grouping_path() {
+        /*
+        GIVEN a pushgateway url, a job and optionally an instance
+        WHEN creating a pusher
+        THEN metrics are pushed to the matching group
+        */
+
+        let pusher = Pusher::new("http://gateway:9091/", "tado", "home").unwrap();
+        assert_eq!(
+            pusher.url.as_str(),
+            "http://gateway:9091/metrics/job/tado/instance/home"
+        );
+
+        let pusher = Pusher::new("http://gateway:9091", "tado", "").unwrap();
+        assert_eq!(pusher.url.as_str(), "http://gateway:9091/metrics/job/tado");
+
+        assert!(Pusher::new("not a url", "tado", "").is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_push() {
+        /*
+        GIVEN a pushgateway that fails the first push
+        WHEN pushing the metrics
+        THEN the push is retried and the rendered metrics are sent
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("PUT"))
+            .and(path("metrics/job/tado/instance/home"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("PUT"))
+            .and(path("metrics/job/tado/instance/home"))
+            .and(header("content-type", "text/plain; version=0.0.4"))
+            .and(body_string_contains(r#"tado_zone_stale{zone="Office"} 1"#))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        set_zone_stale("Office", true);
+
+        let pusher = Pusher::new(&mock_server.uri(), "tado", "home")
+            .unwrap()
+            .with_retry_delay(Duration::from_millis(1));
+
+        // WHEN
+        let actual = pusher.push().await;
+
+        // THEN
+        assert!(actual.is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn test_push_failure() {
+        /*
+        GIVEN a pushgateway that keeps failing
+        WHEN pushing the metrics
+        THEN an error is returned once the retries are exhausted
+        */
+
+        // GIVEN
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let pusher = Pusher::new(&mock_server.uri(), "tado", "")
+            .unwrap()
+            .with_retry_delay(Duration::from_millis(1));
+
+        // WHEN
+        let actual = pusher.push().await;
+
+        // THEN
+        assert!(actual.is_err());
+    }
+}
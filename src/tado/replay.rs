@@ -0,0 +1,193 @@
+//! Replay captured tado° API responses through the models and the metrics
+//! renderer, to catch regressions on real-world payloads.
+//!
+//! Each directory of `tests/fixtures/replay` holds one capture:
+//!
+//! - `zones.json`: the zones of the home, as a list of `{ "name", "state" }`
+//!   objects where `state` is the zone state API response, and the optional
+//!   `away_setting` the setting of the zone's away configuration,
+//! - `weather.json` (optional): the weather API response,
+//! - `home_state.json` (optional): the home state API response,
+//! - `devices.json` (optional): the devices of the home, as a list of
+//!   `{ "zone", "device" }` objects where `device` is the device API response
+//!   and `zone` the name of the zone it serves, if any,
+//! - `expected.prom`: the metrics rendered for the capture.
+//!
+//! To add a capture, anonymize the responses, drop them in a new directory
+//! and run the tests with `UPDATE_REPLAY_FIXTURES=1` to write its
+//! `expected.prom`, then review it. Captures are replayed as if scraped at
+//! `REPLAY_TIME`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde_derive::Deserialize;
+
+use super::clock::FakeClock;
+use super::collector::{Collector, ZoneDataSource};
+use super::metrics::{self, lock_registry};
+use super::model::{
+    DecodedZoneStateApiResponse, DeviceApiResponse, DeviceZoneResponse, HomeStateApiResponse,
+    UnsupportedZoneResponse, WeatherApiResponse, ZoneStateResponse, ZoneStateSettingApiResponse,
+};
+
+const REPLAY_TIME: &str = "2024-01-14T18:05:00Z";
+
+#[derive(Deserialize)]
+struct CapturedZone {
+    name: String,
+    state: DecodedZoneStateApiResponse,
+    #[serde(default)]
+    away_setting: Option<ZoneStateSettingApiResponse>,
+}
+
+#[derive(Deserialize)]
+struct CapturedDevice {
+    #[serde(default)]
+    zone: Option<String>,
+    device: DeviceApiResponse,
+}
+
+struct ReplayDataSource {
+    capture: PathBuf,
+    unsupported_zones: Vec<UnsupportedZoneResponse>,
+}
+
+impl ZoneDataSource for ReplayDataSource {
+    async fn retrieve_zones(&mut self) -> Vec<ZoneStateResponse> {
+        let zones: Vec<CapturedZone> = read_json(&self.capture.join("zones.json"));
+
+        let mut response = Vec::new();
+        for zone in zones {
+            match zone.state {
+                DecodedZoneStateApiResponse::Supported(state) => response.push(ZoneStateResponse {
+                    name: zone.name,
+                    state_response: *state,
+                    scheduled_setting: None,
+                    away_setting: zone.away_setting,
+                }),
+                DecodedZoneStateApiResponse::Unsupported(state) => {
+                    self.unsupported_zones.push(UnsupportedZoneResponse {
+                        name: zone.name,
+                        setting_type: state.setting_type(),
+                    })
+                }
+            }
+        }
+
+        response
+    }
+
+    async fn retrieve_weather(&mut self) -> Option<WeatherApiResponse> {
+        let path = self.capture.join("weather.json");
+
+        path.exists().then(|| read_json(&path))
+    }
+
+    async fn retrieve_home_state(&mut self) -> Option<HomeStateApiResponse> {
+        let path = self.capture.join("home_state.json");
+
+        path.exists().then(|| read_json(&path))
+    }
+
+    async fn retrieve_devices(&mut self) -> Vec<DeviceZoneResponse> {
+        let path = self.capture.join("devices.json");
+        if !path.exists() {
+            return Vec::new();
+        }
+
+        let devices: Vec<CapturedDevice> = read_json(&path);
+
+        devices
+            .into_iter()
+            .map(|captured| DeviceZoneResponse {
+                zone: captured.zone,
+                fault: captured.device.fault(),
+                serial: captured.device.serialNo,
+                device_type: captured.device.deviceType,
+                connected: captured.device.connectionState.map(|state| state.value),
+            })
+            .collect()
+    }
+
+    fn unsupported_zones(&self) -> Vec<UnsupportedZoneResponse> {
+        self.unsupported_zones.clone()
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> T {
+    let content = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("unable to read {}: {}", path.display(), e));
+
+    serde_json::from_str(&content)
+        .unwrap_or_else(|e| panic!("unable to parse {}: {}", path.display(), e))
+}
+
+fn captures() -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+
+    let mut captures: Vec<PathBuf> = fs::read_dir(&root)
+        .unwrap_or_else(|e| panic!("unable to list {}: {}", root.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    captures.sort();
+
+    captures
+}
+
+// Each capture runs on the test's own runtime, so holding the registry lock
+// across await points cannot deadlock.
+#[allow(clippy::await_holding_lock)]
+async fn replay(capture: &Path) -> String {
+    let _registry = lock_registry();
+
+    let mut source = ReplayDataSource {
+        capture: capture.to_path_buf(),
+        unsupported_zones: Vec::new(),
+    };
+    Collector::new(false)
+        .with_clock(Arc::new(FakeClock::at(REPLAY_TIME.parse().unwrap())))
+        .collect(&mut source)
+        .await;
+
+    String::from_utf8(metrics::render()).unwrap()
+}
+
+#[actix_rt::test]
+async fn test_replay_captures() {
+    /*
+    GIVEN the captured API responses
+    WHEN collecting and rendering each of them
+    THEN the rendered metrics match the expected ones
+    */
+
+    let update = std::env::var("UPDATE_REPLAY_FIXTURES").is_ok();
+    let captures = captures();
+    assert!(!captures.is_empty());
+
+    let mut mismatches = Vec::new();
+
+    for capture in captures {
+        let actual = replay(&capture).await;
+        let expected_path = capture.join("expected.prom");
+
+        if update {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        if actual != expected {
+            mismatches.push(format!(
+                "{}:\n--- expected\n{}\n--- actual\n{}",
+                capture.display(),
+                expected,
+                actual
+            ));
+        }
+    }
+
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
@@ -0,0 +1,131 @@
+use std::convert::Infallible;
+
+use hyper::{Body, Request, Response, StatusCode};
+
+use super::{debug, history, homeassistant, logs, metrics, state};
+
+/// The paths the exporter serves its endpoints at, e.g. to expose them under
+/// a prefix behind a reverse proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Routes {
+    pub metrics: String,
+    pub state: String,
+    pub homeassistant: String,
+    pub history: String,
+    pub debug: String,
+    pub logs: String,
+}
+
+impl Routes {
+    /// Serve the metrics at `metrics_path` and the other endpoints at their
+    /// usual path, all of them under `base_path`.
+    pub fn new(base_path: &str, metrics_path: &str) -> Routes {
+        let base_path = base_path.trim_end_matches('/');
+        let path = |path: &str| format!("{}/{}", base_path, path.trim_start_matches('/'));
+
+        Routes {
+            metrics: path(metrics_path),
+            state: path("state.json"),
+            homeassistant: path("homeassistant.json"),
+            history: path("history.json"),
+            debug: path("debug/state"),
+            logs: path("debug/logs"),
+        }
+    }
+}
+
+impl Default for Routes {
+    fn default() -> Routes {
+        Routes::new("", "/metrics")
+    }
+}
+
+pub async fn route(routes: &Routes, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let path = req.uri().path();
+
+    if path == routes.metrics {
+        metrics::renderer(req).await
+    } else if path == routes.state {
+        state::renderer(req).await
+    } else if path == routes.homeassistant {
+        homeassistant::renderer(req).await
+    } else if path == routes.history {
+        history::renderer(req).await
+    } else if path == routes.debug {
+        debug::renderer(req).await
+    } else if path == routes.logs {
+        logs::renderer(req).await
+    } else {
+        Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::Server;
+    use rstest::*;
+
+    #[rstest(
+        base_path,
+        metrics_path,
+        expected,
+        case("", "/metrics", "/metrics"),
+        case("/tado", "/metrics", "/tado/metrics"),
+        case("/tado/", "metrics", "/tado/metrics"),
+        case("", "/tado/metrics", "/tado/metrics")
+    )]
+    fn test_routes(base_path: &str, metrics_path: &str, expected: &str) {
+        let routes = Routes::new(base_path, metrics_path);
+
+        assert_eq!(routes.metrics, expected);
+        assert_eq!(
+            routes.state,
+            format!("{}/state.json", base_path.trim_end_matches('/'))
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_custom_path() {
+        /*
+        GIVEN an HTTP server serving the endpoints under the /tado prefix
+        WHEN scraping the metrics at the custom path, then at the default one
+        THEN the metrics are served at the custom path only
+        */
+
+        // GIVEN
+        let routes = Arc::new(Routes::new("/tado", "/metrics"));
+        let make_svc = make_service_fn(move |_conn| {
+            let routes = routes.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let routes = routes.clone();
+                    async move { route(&routes, req).await }
+                }))
+            }
+        });
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        // WHEN
+        let custom = reqwest::get(format!("http://{}/tado/metrics", addr))
+            .await
+            .unwrap();
+        let default = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap();
+
+        // THEN
+        assert_eq!(custom.status(), reqwest::StatusCode::OK);
+        assert_eq!(default.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+}
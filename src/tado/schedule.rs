@@ -0,0 +1,130 @@
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+
+use super::model::TimetableBlockApiResponse;
+
+/// Find the block of a zone's timetable active at the given local time.
+pub fn active_block(
+    blocks: &[TimetableBlockApiResponse],
+    now: NaiveDateTime,
+) -> Option<&TimetableBlockApiResponse> {
+    let time = now.time();
+
+    blocks.iter().find(|block| {
+        if !covers(&block.dayType, now.weekday()) {
+            return false;
+        }
+
+        let (start, end) = match (parse_time(&block.start), parse_time(&block.end)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return false,
+        };
+
+        start <= time && (end == NaiveTime::MIN || time < end)
+    })
+}
+
+// Timetables either have a single day type for the whole week, one for week
+// days and one per weekend day, or one per day of the week.
+fn covers(day_type: &str, weekday: Weekday) -> bool {
+    match day_type {
+        "MONDAY_TO_SUNDAY" => true,
+        "MONDAY_TO_FRIDAY" => weekday.num_days_from_monday() < 5,
+        day_type => day_type == day_name(weekday),
+    }
+}
+
+fn day_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MONDAY",
+        Weekday::Tue => "TUESDAY",
+        Weekday::Wed => "WEDNESDAY",
+        Weekday::Thu => "THURSDAY",
+        Weekday::Fri => "FRIDAY",
+        Weekday::Sat => "SATURDAY",
+        Weekday::Sun => "SUNDAY",
+    }
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::*;
+
+    fn blocks(response_str: &str) -> Vec<TimetableBlockApiResponse> {
+        serde_json::from_str(response_str).unwrap()
+    }
+
+    const THREE_DAY_BLOCKS: &str = r#"[
+        {
+            "dayType": "MONDAY_TO_FRIDAY",
+            "start": "00:00",
+            "end": "07:00",
+            "geolocationOverride": false,
+            "setting": { "type": "HEATING", "power": "ON", "temperature": { "celsius": 17.0, "fahrenheit": 62.6 } }
+        },
+        {
+            "dayType": "MONDAY_TO_FRIDAY",
+            "start": "07:00",
+            "end": "00:00",
+            "geolocationOverride": false,
+            "setting": { "type": "HEATING", "power": "ON", "temperature": { "celsius": 20.5, "fahrenheit": 68.9 } }
+        },
+        {
+            "dayType": "SATURDAY",
+            "start": "00:00",
+            "end": "00:00",
+            "geolocationOverride": false,
+            "setting": { "type": "HEATING", "power": "ON", "temperature": { "celsius": 19.0, "fahrenheit": 66.2 } }
+        },
+        {
+            "dayType": "SUNDAY",
+            "start": "00:00",
+            "end": "00:00",
+            "geolocationOverride": false,
+            "setting": { "type": "HEATING", "power": "OFF", "temperature": null }
+        }
+    ]"#;
+
+    #[rstest(now, expected_celsius,
+        // Monday
+        case("2022-11-21T06:59:00", Some(17.0)),
+        case("2022-11-21T07:00:00", Some(20.5)),
+        case("2022-11-21T23:59:00", Some(20.5)),
+        // Saturday
+        case("2022-11-26T12:00:00", Some(19.0)),
+        // Sunday, when heating is scheduled off
+        case("2022-11-27T12:00:00", None),
+    )]
+    fn test_active_block(now: &str, expected_celsius: Option<f64>) {
+        let blocks = blocks(THREE_DAY_BLOCKS);
+
+        let actual = active_block(&blocks, now.parse().unwrap()).unwrap();
+
+        assert_eq!(
+            actual.setting.temperature.as_ref().map(|t| t.celsius),
+            expected_celsius
+        );
+    }
+
+    #[test]
+    fn test_active_block_none() {
+        let blocks = blocks(
+            r#"[
+                {
+                    "dayType": "MONDAY",
+                    "start": "08:00",
+                    "end": "18:00",
+                    "setting": { "type": "HEATING", "power": "ON", "temperature": { "celsius": 20.0, "fahrenheit": 68.0 } }
+                }
+            ]"#,
+        );
+
+        assert!(active_block(&blocks, "2022-11-21T07:00:00".parse().unwrap()).is_none());
+        assert!(active_block(&blocks, "2022-11-22T12:00:00".parse().unwrap()).is_none());
+    }
+}
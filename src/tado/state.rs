@@ -0,0 +1,160 @@
+use std::convert::Infallible;
+use std::sync::RwLock;
+
+use super::model::{WeatherApiResponse, ZoneStateResponse};
+
+use hyper::{header::CONTENT_TYPE, Body, Request, Response};
+use lazy_static::lazy_static;
+use serde_derive::Serialize;
+
+/// The data retrieved during the latest scrape, as exposed on `/state.json`.
+///
+/// Only API models are stored here, so tokens and credentials held by the
+/// client can never end up in the dump.
+#[derive(Serialize, Default)]
+pub struct State {
+    pub zones: Vec<ZoneStateResponse>,
+    pub weather: Option<WeatherApiResponse>,
+}
+
+lazy_static! {
+    static ref STATE: RwLock<State> = RwLock::new(State::default());
+}
+
+pub fn set_state(zones: Vec<ZoneStateResponse>, weather: Option<WeatherApiResponse>) {
+    let mut state = STATE.write().unwrap();
+    *state = State { zones, weather };
+}
+
+/// Read the data retrieved during the latest scrape.
+pub fn read<T>(f: impl FnOnce(&State) -> T) -> T {
+    f(&STATE.read().unwrap())
+}
+
+pub async fn renderer(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let buffer = serde_json::to_vec(&*STATE.read().unwrap()).unwrap();
+
+    let response = Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(buffer))
+        .unwrap();
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tado::metrics::lock_registry;
+    use crate::tado::model::{
+        PercentageDataType, TemperatureDataType, WeatherOutsideTemperatureApiResponse,
+        WeatherSolarIntensityApiResponse,
+    };
+
+    use hyper::body;
+    use serde_json::{json, Value};
+
+    #[allow(clippy::await_holding_lock)]
+    #[actix_rt::test]
+    async fn test_renderer() {
+        /*
+        GIVEN a stored state with one zone and weather data
+        WHEN the state renderer is called
+        THEN the JSON document mirrors the API models
+        */
+
+        let _registry = lock_registry();
+
+        // GIVEN
+        let zone = ZoneStateResponse {
+            name: "Office".to_string(),
+            state_response: serde_json::from_str(
+                r#"{
+                    "setting": {
+                        "type": "HEATING",
+                        "temperature": { "celsius": 21.0, "fahrenheit": 69.8 }
+                    },
+                    "activityDataPoints": {
+                        "heatingPower": { "percentage": 12.0 }
+                    },
+                    "sensorDataPoints": {
+                        "insideTemperature": { "celsius": 20.5, "fahrenheit": 68.9 },
+                        "humidity": { "percentage": 48.2 }
+                    }
+                }"#,
+            )
+            .unwrap(),
+            scheduled_setting: None,
+            away_setting: None,
+        };
+        let weather = WeatherApiResponse {
+            solarIntensity: WeatherSolarIntensityApiResponse {
+                dataType: PercentageDataType::Percentage,
+                percentage: 18.3,
+            },
+            outsideTemperature: WeatherOutsideTemperatureApiResponse {
+                dataType: TemperatureDataType::Temperature,
+                celsius: 10.0,
+                fahrenheit: 50.0,
+                timestamp: None,
+            },
+            weatherState: None,
+        };
+
+        set_state(vec![zone], Some(weather));
+
+        // WHEN
+        let response = renderer(Request::new(Body::empty())).await.unwrap();
+
+        // THEN
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+
+        let actual: Value =
+            serde_json::from_slice(&body::to_bytes(response.into_body()).await.unwrap()).unwrap();
+
+        assert_eq!(
+            actual,
+            json!({
+                "zones": [{
+                    "name": "Office",
+                    "state_response": {
+                        "tadoMode": null,
+                        "setting": {
+                            "type": "HEATING",
+                            "power": null,
+                            "temperature": { "celsius": 21.0, "fahrenheit": 69.8 }
+                        },
+                        "activityDataPoints": {
+                            "heatingPower": { "percentage": 12.0 },
+                            "acPower": null
+                        },
+                        "sensorDataPoints": {
+                            "insideTemperature": {
+                                "celsius": 20.5,
+                                "fahrenheit": 68.9,
+                                "timestamp": null
+                            },
+                            "humidity": { "percentage": 48.2 }
+                        },
+                        "openWindow": null,
+                        "overlay": null,
+                        "nextScheduleChange": null
+                    },
+                    "scheduled_setting": null,
+                    "away_setting": null
+                }],
+                "weather": {
+                    "solarIntensity": { "type": "PERCENTAGE", "percentage": 18.3 },
+                    "outsideTemperature": {
+                        "type": "TEMPERATURE",
+                        "fahrenheit": 50.0,
+                        "celsius": 10.0
+                    },
+                    "weatherState": null
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::f64::consts::PI;
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+
+// The sun is up once its upper edge clears the horizon, accounting for the
+// atmospheric refraction.
+const SUNRISE_ELEVATION_DEGREES: f64 = -0.833;
+
+/// Whether the sun is up at the given location and time.
+///
+/// tado° provides no sunrise nor sunset times, so the sun elevation is
+/// computed from the NOAA solar position approximation, accurate to about a
+/// minute around sunrise and sunset.
+pub fn is_daylight(latitude: f64, longitude: f64, at: DateTime<Utc>) -> bool {
+    elevation(latitude, longitude, at) > SUNRISE_ELEVATION_DEGREES
+}
+
+// The elevation of the sun above the horizon, in degrees.
+fn elevation(latitude: f64, longitude: f64, at: DateTime<Utc>) -> f64 {
+    let minutes = (at.hour() * 60 + at.minute()) as f64 + at.second() as f64 / 60.0;
+
+    // fractional year, in radians
+    let gamma = 2.0 * PI / 365.0 * (at.ordinal0() as f64 + (minutes / 60.0 - 12.0) / 24.0);
+
+    // equation of time, in minutes
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2.0 * gamma).cos()
+            - 0.040849 * (2.0 * gamma).sin());
+
+    // declination of the sun, in radians
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos()
+        + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos()
+        + 0.00148 * (3.0 * gamma).sin();
+
+    let true_solar_minutes = minutes + equation_of_time + 4.0 * longitude;
+    let hour_angle = (true_solar_minutes / 4.0 - 180.0).to_radians();
+
+    let latitude = latitude.to_radians();
+    let cos_zenith =
+        latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+
+    90.0 - cos_zenith.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest(latitude, longitude, at, expected,
+        // Paris, at noon and at night
+        case(48.85, 2.35, "2024-06-21T12:00:00Z", true),
+        case(48.85, 2.35, "2024-06-21T23:00:00Z", false),
+        // Paris, around the sunrise at 05:47 local time (03:47 UTC)
+        case(48.85, 2.35, "2024-06-21T03:40:00Z", false),
+        case(48.85, 2.35, "2024-06-21T03:55:00Z", true),
+        // Sydney, at local noon in the southern winter
+        case(-33.87, 151.21, "2024-06-21T02:00:00Z", true),
+        // Tromsø, during the midnight sun and the polar night
+        case(69.65, 18.96, "2024-06-21T23:00:00Z", true),
+        case(69.65, 18.96, "2024-12-21T11:00:00Z", false),
+    )]
+    fn test_is_daylight(latitude: f64, longitude: f64, at: &str, expected: bool) {
+        let at = at.parse::<DateTime<Utc>>().unwrap();
+
+        assert_eq!(is_daylight(latitude, longitude, at), expected);
+    }
+}
@@ -0,0 +1,182 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeZone};
+use cron::Schedule;
+use log::warn;
+use tokio::time::{Instant, Interval};
+
+// The number of upcoming times of a cron expression looked at for the
+// shortest interval between two of them, e.g. a day of scrapes every 15
+// minutes.
+const CRON_LOOKAHEAD: usize = 100;
+
+/// What drives the scrapes: a fixed interval, or the times of a cron
+/// expression, e.g. to align scrapes on the minute or only scrape during the
+/// day.
+pub enum Ticker {
+    Interval(Interval),
+    Cron(Box<Schedule>),
+}
+
+impl Ticker {
+    /// Tick every `period`, starting one period from now. The ticker keeps
+    /// counting down while a scrape is running, so scrapes don't drift.
+    pub fn interval(period: Duration) -> Ticker {
+        Ticker::Interval(tokio::time::interval_at(Instant::now() + period, period))
+    }
+
+    /// Tick at the times of a cron expression, in the local time zone of the
+    /// exporter. Both standard expressions, starting with the minutes, and
+    /// expressions starting with the seconds are accepted.
+    pub fn cron(expression: &str) -> Result<Ticker, String> {
+        let with_seconds = match expression.split_whitespace().count() {
+            5 => format!("0 {}", expression),
+            _ => expression.to_string(),
+        };
+
+        Schedule::from_str(&with_seconds)
+            .map(|schedule| Ticker::Cron(Box::new(schedule)))
+            .map_err(|e| format!("invalid cron expression {}: {}", expression, e))
+    }
+
+    /// The shortest time between two ticks, e.g. to bound scrapes so they
+    /// never pile up. Cron expressions may tick irregularly, so the shortest
+    /// interval between their upcoming times is used, none without two
+    /// upcoming times.
+    pub fn shortest_period(&self) -> Option<Duration> {
+        match self {
+            Ticker::Interval(interval) => Some(interval.period()),
+            Ticker::Cron(schedule) => shortest_period(schedule, &Local::now()),
+        }
+    }
+
+    /// Wait until the next scrape is due.
+    pub async fn tick(&mut self) {
+        match self {
+            Ticker::Interval(interval) => {
+                interval.tick().await;
+            }
+            Ticker::Cron(schedule) => {
+                let now = Local::now();
+                match next_run(schedule, &now) {
+                    Some(next) => {
+                        let wait = (next - now).to_std().unwrap_or_default();
+                        tokio::time::sleep(wait).await;
+                    }
+                    None => {
+                        warn!("the cron expression has no upcoming time, not scraping anymore");
+                        std::future::pending::<()>().await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The first time of the schedule strictly after `after`, if any.
+fn next_run<Z: TimeZone>(schedule: &Schedule, after: &DateTime<Z>) -> Option<DateTime<Z>> {
+    schedule.after(after).next()
+}
+
+// The shortest interval between the upcoming times of the schedule after
+// `after`, if any.
+fn shortest_period<Z: TimeZone>(schedule: &Schedule, after: &DateTime<Z>) -> Option<Duration> {
+    let times: Vec<DateTime<Z>> = schedule.after(after).take(CRON_LOOKAHEAD).collect();
+
+    times
+        .windows(2)
+        .filter_map(|pair| {
+            pair[1]
+                .clone()
+                .signed_duration_since(pair[0].clone())
+                .to_std()
+                .ok()
+        })
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    use rstest::*;
+
+    fn schedule(expression: &str) -> Schedule {
+        match Ticker::cron(expression).unwrap() {
+            Ticker::Cron(schedule) => *schedule,
+            Ticker::Interval(_) => unreachable!(),
+        }
+    }
+
+    #[rstest(
+        expression,
+        after,
+        expected,
+        // every 5 minutes, on the minute
+        case("*/5 * * * *", "2024-01-14T18:03:27Z", "2024-01-14T18:05:00Z"),
+        case("*/5 * * * *", "2024-01-14T18:05:00Z", "2024-01-14T18:10:00Z"),
+        // every 30 seconds
+        case("*/30 * * * * *", "2024-01-14T18:03:27Z", "2024-01-14T18:03:30Z"),
+        // every 10 minutes during the day only
+        case("*/10 8-19 * * *", "2024-01-14T19:55:00Z", "2024-01-15T08:00:00Z")
+    )]
+    fn test_next_run(expression: &str, after: &str, expected: &str) {
+        /*
+        GIVEN a cron expression
+        WHEN computing its next run after a given time
+        THEN the next time matching the expression is returned
+        */
+
+        // GIVEN
+        let schedule = schedule(expression);
+        let after = after.parse::<DateTime<Utc>>().unwrap();
+
+        // WHEN
+        let actual = next_run(&schedule, &after);
+
+        // THEN
+        assert_eq!(actual, Some(expected.parse::<DateTime<Utc>>().unwrap()));
+    }
+
+    #[rstest(expression, case(""), case("every 5 minutes"), case("*/5 * * *"))]
+    fn test_cron_invalid(expression: &str) {
+        /*
+        GIVEN an invalid cron expression
+        WHEN creating a ticker from it
+        THEN an error is returned
+        */
+
+        assert!(Ticker::cron(expression).is_err());
+    }
+
+    #[rstest(
+        expression,
+        expected,
+        case("*/5 * * * *", Some(300)),
+        // the night doesn't count, scrapes are 10 minutes apart during the day
+        case("*/10 8-19 * * *", Some(600)),
+        case("*/15 * * * * *", Some(15)),
+        // only in the past
+        case("0 0 12 1 1 * 2020", None)
+    )]
+    fn test_shortest_period(expression: &str, expected: Option<u64>) {
+        /*
+        GIVEN a cron expression
+        WHEN computing the shortest interval between its upcoming times
+        THEN the interval is returned, none without upcoming times
+        */
+
+        // GIVEN
+        let schedule = schedule(expression);
+        let after = "2024-01-14T19:55:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        // WHEN
+        let actual = shortest_period(&schedule, &after);
+
+        // THEN
+        assert_eq!(actual, expected.map(Duration::from_secs));
+    }
+}
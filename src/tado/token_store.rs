@@ -0,0 +1,134 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use log::warn;
+use serde_derive::{Deserialize, Serialize};
+
+/// An access token to the tado° API, as persisted across restarts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StoredToken {
+    pub access_token: String,
+    // in seconds since the epoch, as instants don't survive restarts
+    pub expires_at: i64,
+}
+
+/// The access token, stored as JSON in a file only readable by its owner.
+pub struct TokenStore {
+    path: PathBuf,
+}
+
+impl TokenStore {
+    pub fn new(path: PathBuf) -> TokenStore {
+        TokenStore { path }
+    }
+
+    /// Read the token, none when the file doesn't exist yet or can't be read.
+    pub fn load(&self) -> Option<StoredToken> {
+        let content = match fs::read(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("unable to read token file {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+
+        serde_json::from_slice(&content)
+            .map_err(|e| warn!("ignoring invalid token file {}: {}", self.path.display(), e))
+            .ok()
+    }
+
+    /// Write the token, which is only an optimization: failures are logged.
+    /// The token is written to a temporary file first, then renamed, so a
+    /// crash never leaves a truncated token behind.
+    pub fn store(&self, token: &StoredToken) {
+        if let Err(e) = self.write(&serde_json::to_vec(token).unwrap()) {
+            warn!("unable to write token file {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn write(&self, content: &[u8]) -> io::Result<()> {
+        let mut temporary_path = self.path.clone().into_os_string();
+        temporary_path.push(".tmp");
+        let temporary_path = PathBuf::from(temporary_path);
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(&temporary_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+
+        fs::rename(&temporary_path, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_store() {
+        /*
+        GIVEN a token file that doesn't exist yet
+        WHEN loading it, then storing a token and loading it again
+        THEN there is no token at first, then the file holds the token, is only
+             readable by its owner, and no temporary file is left behind
+        */
+
+        // GIVEN
+        let path = std::env::temp_dir().join(format!("tado-token-{}.json", std::process::id()));
+        let token_store = TokenStore::new(path.clone());
+        let token = StoredToken {
+            access_token: "access-token".to_string(),
+            expires_at: 1705255200,
+        };
+
+        // WHEN
+        let before = token_store.load();
+        token_store.store(&token);
+        let after = token_store.load();
+        let content = fs::read_to_string(&path).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        let temporary_exists = path.with_extension("json.tmp").exists();
+        fs::remove_file(&path).unwrap();
+
+        // THEN
+        assert_eq!(before, None);
+        assert_eq!(after, Some(token));
+        assert_eq!(
+            content,
+            r#"{"access_token":"access-token","expires_at":1705255200}"#
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777,
+            0o600
+        );
+        assert!(!temporary_exists);
+    }
+
+    #[test]
+    fn test_token_store_invalid() {
+        /*
+        GIVEN a token file that isn't valid JSON, e.g. truncated by hand
+        WHEN loading it
+        THEN there is no token, so the exporter authenticates
+        */
+
+        // GIVEN
+        let path =
+            std::env::temp_dir().join(format!("tado-token-invalid-{}.json", std::process::id()));
+        fs::write(&path, r#"{"access_token":"#).unwrap();
+
+        // WHEN
+        let token = TokenStore::new(path.clone()).load();
+        fs::remove_file(&path).unwrap();
+
+        // THEN
+        assert_eq!(token, None);
+    }
+}